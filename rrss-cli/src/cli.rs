@@ -1,4 +1,6 @@
-use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -9,11 +11,38 @@ pub struct Cli {
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     Dry,
+    CheckConfig,
     // DumpConfig,
     // DumpSources,
     DumpOpml,
     Fetch,
     Clear,
+    Health,
+    Daemon,
+    ImportOpml {
+        file: PathBuf,
+    },
+    Add {
+        url: String,
+        #[arg(short, long)]
+        tag: Vec<String>,
+        /// subscribe to the url as given instead of looking for feeds on it
+        #[arg(long)]
+        no_discover: bool,
+    },
+    Remove {
+        url: String,
+    },
+    Tag {
+        url: String,
+        tag: String,
+    },
+    #[command(group(ArgGroup::new("changes").required(true).multiple(true)))]
+    Edit {
+        url: String,
+        #[arg(long, group = "changes")]
+        max_items: Option<u32>,
+    },
     Query {
         #[command(subcommand)]
         query: QueryTarget,
@@ -22,6 +51,16 @@ pub enum Commands {
         feed_url: String,
         item_url: String,
     },
+    MarkAsUnread {
+        feed_url: String,
+        item_url: String,
+    },
+    MarkAllAsRead {
+        #[arg(short, long)]
+        tag: Option<String>,
+        #[arg(long)]
+        older_than: Option<String>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -41,6 +80,8 @@ pub enum QueryCommand {
     All,
     Read,
     Unread,
+    Starred,
     Tag { tag: String },
     Contains { pattern: String },
+    Match { query: String },
 }
@@ -0,0 +1,121 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use directories::ProjectDirs;
+use rrss_core::config::config_modified;
+use rrss_core::feed_manager::{FeedManager, TaskStatus};
+use rrss_core::globals::{LOG_FILE, PID_FILE, PROJECT_NAME};
+use signal_hook::consts::{SIGINT, SIGTERM};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+// the pidfile is taken before the cache is opened, so a second daemon
+// exits without touching it
+pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let proj = ProjectDirs::from("", "", PROJECT_NAME).unwrap();
+    fs::create_dir_all(proj.data_dir())?;
+    let _pidfile = PidFile::acquire(proj.data_dir())?;
+    // a detached daemon has no terminal, so it logs to a file instead
+    let mut log = Log::open(proj.data_dir())?;
+    let mut fm = match FeedManager::new() {
+        Ok(fm) => fm,
+        Err(e) => {
+            log.write(format!("failed to open the cache: {e}"));
+            return Err(e.into());
+        }
+    };
+    let stop = Arc::new(AtomicBool::new(false));
+    let _ = signal_hook::flag::register(SIGTERM, stop.clone())?;
+    let _ = signal_hook::flag::register(SIGINT, stop.clone())?;
+
+    log.write(format!("started (pid {})", std::process::id()));
+    if let Some(warning) = fm.cache_warning() {
+        log.write(format!("warning: {warning}"));
+    }
+    fm.start_scheduler();
+    let mut modified = config_modified();
+    let mut reloaded = Instant::now();
+    while !stop.load(Ordering::Relaxed) {
+        // sources edited through the cli and items read in the tui are
+        // picked up before the scheduler looks for due feeds
+        let changed = config_modified() != modified;
+        if changed || reloaded.elapsed() >= RELOAD_INTERVAL {
+            modified = config_modified();
+            reloaded = Instant::now();
+            match fm.reload() {
+                Ok(errors) if changed => {
+                    for e in errors {
+                        log.write(format!("config: {e}"));
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => log.write(format!("failed to reload: {e}")),
+            }
+        }
+        match fm.poll_scheduler() {
+            TaskStatus::Error(e) => log.write(e),
+            TaskStatus::Done((updated, errs)) => {
+                log.write(format!("updated {} feeds", updated.len()));
+                for e in errs {
+                    log.write(e.to_string());
+                }
+            }
+            TaskStatus::None | TaskStatus::Running => {}
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    fm.stop_scheduler();
+    log.write("stopped".into());
+    Ok(())
+}
+
+struct Log(File);
+impl Log {
+    fn open(dir: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE))?;
+        Ok(Self(file))
+    }
+    fn write(&mut self, msg: String) {
+        let now = Local::now().format("%Y-%m-%d %H:%M:%S");
+        let _ = writeln!(self.0, "[{now}] {msg}");
+    }
+}
+
+struct PidFile {
+    path: PathBuf,
+    _file: File,
+}
+impl PidFile {
+    fn acquire(dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = dir.join(PID_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        if file.try_lock().is_err() {
+            let mut pid = String::new();
+            let _ = file.read_to_string(&mut pid)?;
+            return Err(format!("another daemon is already running (pid {})", pid.trim()).into());
+        }
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+        file.sync_all()?;
+        Ok(Self { path, _file: file })
+    }
+}
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -1,37 +1,169 @@
 #![feature(let_chains)]
 #![warn(unused_results)]
 
+use std::io::Write;
+
 use clap::Parser;
 use cli::{Cli, Commands, QueryCommand, QueryTarget};
+use rrss_core::config::{check_config, FeedSource, OpmlImport, SourcesFile};
+use rrss_core::discovery::{discover, FeedCandidate};
+use rrss_core::error::FetchError;
 use rrss_core::feed_manager::{FeedManager, TaskStatus};
-use rrss_core::filter::Filter;
-use rrss_core::models::ItemId;
+use rrss_core::filter::{parse_date, Expr, Filter, Predicate};
+use rrss_core::globals::CONFIG;
+use rrss_core::models::{Feed, FeedId, ItemId};
 use rrss_core::sorter::Sorter;
 
 mod cli;
+mod daemon;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Cli::parse();
-    let mut fm = FeedManager::new();
+    if let Commands::Daemon = args.command {
+        return daemon::run();
+    }
+    // validating the config shouldn't touch the cache
+    if let Commands::CheckConfig = args.command {
+        let errors = check_config();
+        for e in &errors {
+            println!("{e}");
+        }
+        if !errors.is_empty() {
+            std::process::exit(1);
+        }
+        println!("no problems found");
+        return Ok(());
+    }
+    let mut fm = match FeedManager::new() {
+        Ok(fm) => fm,
+        Err(e) => {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+    };
+    if let Some(warning) = fm.cache_warning() {
+        eprintln!("warning: {warning}");
+    }
 
     match args.command {
-        Commands::Dry => {}
+        Commands::Dry => {
+            for feed in fm.get_feeds(&Filter::new(), &Sorter::NONE) {
+                let fired = feed
+                    .items()
+                    .into_iter()
+                    .flatten()
+                    .filter(|i| !i.state.effects.fired.is_empty())
+                    .collect::<Vec<_>>();
+                if fired.is_empty() {
+                    continue;
+                }
+                println!("{}", feed.name());
+                for i in fired {
+                    println!(
+                        "  {}  [{}]",
+                        i.data.title.clone().unwrap_or_else(|| i.data.id.1.clone()),
+                        i.state.effects.fired.join(", ")
+                    );
+                }
+            }
+        }
         Commands::Fetch => {
             println!("fetching...");
             async_std::task::block_on(fm.update_feeds(&Filter::new(), || {}));
             match fm.poll_update_feeds() {
                 TaskStatus::None | TaskStatus::Running => unreachable!(),
                 TaskStatus::Error(e) => eprintln!("{e}"),
-                TaskStatus::Done((errs, save_handle)) => {
+                TaskStatus::Done(errs) => {
                     println!("done");
-                    if !errs.is_empty() {
-                        eprintln!("{:?}", errs);
+                    print_errors(&errs);
+                }
+            }
+        }
+        Commands::Clear => fm.clear()?,
+        Commands::Daemon | Commands::CheckConfig => unreachable!(),
+        Commands::ImportOpml { file } => {
+            let import = OpmlImport::from_file(&file)?;
+            for reason in &import.skipped {
+                eprintln!("[skipped] {reason}");
+            }
+            let mut sources = SourcesFile::open()?;
+            let (mut added, mut updated) = (0, 0);
+            for source in &import.sources {
+                if !sources.contains(&source.url) {
+                    sources.add(source)?;
+                    added += 1;
+                    continue;
+                }
+                // feeds already subscribed to keep their settings but gain
+                // the folders they were filed under
+                let mut changed = false;
+                for tag in &source.tags {
+                    changed |= sources.add_tag(&source.url, tag)?;
+                }
+                if changed {
+                    updated += 1;
+                }
+            }
+            sources.save()?;
+            println!(
+                "added {} feeds, updated {} ({} unchanged, {} skipped)",
+                added,
+                updated,
+                import.sources.len() - added - updated,
+                import.skipped.len()
+            );
+        }
+        Commands::Health => {
+            let feeds = fm.get_health(&Filter::new().unhealthy(), &Feed::BY_FAILURES_REV);
+            let width = feeds.iter().map(|f| f.id.0.len()).max().unwrap_or_default();
+            for f in &feeds {
+                println!(
+                    "{:<width$}  {:>5}  {:<25}  {:<6}  {}",
+                    f.id.0,
+                    f.failure_streak,
+                    f.last_success
+                        .map(|d| d.to_rfc3339())
+                        .unwrap_or_else(|| "never".into()),
+                    f.last_status.map(|s| s.to_string()).unwrap_or_default(),
+                    f.last_error.clone().unwrap_or_default(),
+                    width = width
+                );
+            }
+            if feeds
+                .iter()
+                .any(|f| f.failure_streak >= CONFIG.get().failure_threshold)
+            {
+                std::process::exit(1);
+            }
+        }
+        Commands::Add {
+            url,
+            tag,
+            no_discover,
+        } => {
+            if no_discover {
+                fm.add_feed(FeedSource::new(FeedId(url.clone()), tag))?;
+                println!("added {url}");
+            } else {
+                let candidates = discover(&url, CONFIG.get().fetch_timeout)?;
+                match pick_candidate(&candidates)? {
+                    Some(candidate) => {
+                        fm.add_feed(FeedSource::new(candidate.url.clone(), tag))?;
+                        println!("added {}", candidate.url.0);
+                    }
+                    None => {
+                        eprintln!("no feeds found at {url}, use --no-discover to add it anyway")
                     }
-                    save_handle.join().expect("failed to save feeds");
                 }
             }
         }
-        Commands::Clear => fm.clear(),
+        Commands::Remove { url } => fm.remove_feed(FeedId(url))?,
+        Commands::Tag { url, tag } => fm.tag_feed(FeedId(url), &tag)?,
+        Commands::Edit { url, max_items } => {
+            if let Some(max_items) = max_items {
+                fm.set_max_items(FeedId(url), max_items)?;
+            }
+        }
         Commands::Query { query } => match query {
             QueryTarget::Feed { query } => match query {
                 QueryCommand::Read => {
@@ -46,6 +178,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!("{:?}", r.id());
                     }
                 }
+                QueryCommand::Starred => {
+                    let filter = Filter::new().query(Expr::Pred(Predicate::Starred));
+                    let res = fm.get_feeds(&filter, &Sorter::NONE);
+                    for r in res {
+                        println!("{:?}", r.id());
+                    }
+                }
                 QueryCommand::All => {
                     let res = fm.get_feeds(&Filter::new(), &Sorter::NONE);
                     for r in res {
@@ -64,51 +203,151 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!("{:?}", r);
                     }
                 }
+                QueryCommand::Match { query } => {
+                    let res = fm.get_feeds(&parse_filter(&query), &Sorter::NONE);
+                    for r in res {
+                        println!("{:?}", r.id());
+                    }
+                }
             },
             QueryTarget::Item { query } => match query {
                 QueryCommand::Read => {
-                    let res = fm.get_items(&Filter::new().read_item(), &Sorter::NONE);
+                    let res = fm.get_items(&Filter::new().read_item().visible(), &Sorter::NONE);
                     for r in res {
                         println!("{:?}", r.data.id);
                     }
                 }
                 QueryCommand::Unread => {
-                    let res = fm.get_items(&Filter::new().unread_item(), &Sorter::NONE);
+                    let res = fm.get_items(&Filter::new().unread_item().visible(), &Sorter::NONE);
+                    for r in res {
+                        println!("{:?}", r.data.id);
+                    }
+                }
+                QueryCommand::Starred => {
+                    let res = fm.get_items(&Filter::new().starred().visible(), &Sorter::NONE);
                     for r in res {
                         println!("{:?}", r.data.id);
                     }
                 }
                 QueryCommand::All => {
-                    let res = fm.get_items(&Filter::new(), &Sorter::NONE);
+                    let res = fm.get_items(&Filter::new().visible(), &Sorter::NONE);
                     for r in res {
                         println!("{:?}", r.data.id);
                     }
                 }
                 QueryCommand::Tag { tag } => {
-                    let res = fm.get_items(&Filter::new().tag_id(tag), &Sorter::NONE);
+                    let res = fm.get_items(&Filter::new().tag_id(tag).visible(), &Sorter::NONE);
                     for r in res {
                         println!("{:?}", r);
                     }
                 }
                 QueryCommand::Contains { pattern } => {
-                    let res = fm.get_items(&Filter::new().item_contains(pattern), &Sorter::NONE);
+                    let res = fm.get_items(
+                        &Filter::new().item_contains(pattern).visible(),
+                        &Sorter::NONE,
+                    );
                     for r in res {
                         println!("{:?}", r.data.title);
                     }
                 }
+                QueryCommand::Match { query } => {
+                    let res = fm.get_items(&parse_filter(&query).visible(), &Sorter::NONE);
+                    for r in res {
+                        println!("{:?}", r.data.id);
+                    }
+                }
             },
         },
         Commands::MarkAsRead { feed_url, item_url } => {
-            match fm.mark_item_as_read(ItemId(feed_url, item_url)) {
-                Some(save_handle) => save_handle.join().expect("failed to save feeds"),
+            let id = ItemId(feed_url, item_url);
+            match fm.get_item(id.clone()) {
+                Some(_) => fm.mark_item_as_read(id)?,
+                None => println!("item not found"),
+            }
+        }
+        Commands::MarkAsUnread { feed_url, item_url } => {
+            let id = ItemId(feed_url, item_url);
+            match fm.get_item(id.clone()) {
+                Some(_) => fm.mark_item_as_unread(id)?,
                 None => println!("item not found"),
             }
         }
+        Commands::MarkAllAsRead { tag, older_than } => {
+            let mut filter = Filter::new();
+            if let Some(tag) = tag {
+                filter = filter.tag_id(tag);
+            }
+            if let Some(date) = older_than {
+                match parse_date(&date) {
+                    Some(date) => filter = filter.posted_before(date),
+                    None => {
+                        eprintln!("invalid date '{date}', expected e.g. 7d, 2w, 2024-01-31");
+                        std::process::exit(2);
+                    }
+                }
+            }
+            fm.mark_items_as_read(&filter)?;
+        }
         Commands::DumpOpml => {
-            let opml = fm.as_opml();
-            println!("{}", opml.to_string().unwrap());
+            println!("{}", fm.as_opml().to_xml()?);
         }
     };
 
     Ok(())
 }
+
+fn parse_filter(query: &str) -> Filter {
+    match Filter::parse(query) {
+        Ok(filter) => filter,
+        Err(e) => {
+            eprintln!("{query}");
+            eprintln!("{:>1$}", "^", query[..e.pos].chars().count() + 1);
+            eprintln!("error: {}", e.msg);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn print_errors(errs: &[FetchError]) {
+    let width = errs
+        .iter()
+        .map(|e| e.id().0.len())
+        .max()
+        .unwrap_or_default();
+    for e in errs {
+        eprintln!(
+            "{:<width$}  {:<8}  {}",
+            e.id().0,
+            e.kind(),
+            e.reason(),
+            width = width
+        );
+    }
+}
+
+fn pick_candidate(candidates: &[FeedCandidate]) -> std::io::Result<Option<&FeedCandidate>> {
+    if candidates.len() <= 1 {
+        return Ok(candidates.first());
+    }
+    for (i, c) in candidates.iter().enumerate() {
+        println!(
+            "{:>3}  {:<5}  {}  ({})",
+            i + 1,
+            c.feed_type.to_string(),
+            c.title,
+            c.url.0
+        );
+    }
+    loop {
+        print!("pick a feed [1-{}]: ", candidates.len());
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        match line.trim().parse::<usize>() {
+            Ok(n) if (1..=candidates.len()).contains(&n) => return Ok(candidates.get(n - 1)),
+            _ => eprintln!("invalid choice"),
+        }
+    }
+}
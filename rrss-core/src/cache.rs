@@ -1,55 +1,955 @@
-use std::fs::{self, OpenOptions};
-use std::io::{Read, Write};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use directories::ProjectDirs;
+use rusqlite::{params, Connection, Transaction};
 
-use crate::globals::{CACHE_FILE, PROJECT_NAME};
-use crate::models::Feed;
+use crate::error::CacheError;
+use crate::globals::{CACHE_FILE, CONFIG, LEGACY_CACHE_FILE, PROJECT_NAME};
+use crate::models::{
+    Feed, FeedData, FeedId, FeedState, Item, ItemData, ItemId, ItemState, RuleEffects,
+};
 
-pub struct CachedFeeds;
+const BACKUP_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
+
+const MAGIC: i32 = 0x7272_7373;
+const MIGRATIONS: &[fn(&Transaction) -> rusqlite::Result<()>] = &[migrate_v1, migrate_v2];
+
+pub struct CachedFeed {
+    pub id: FeedId,
+    pub state: FeedState,
+    pub data: Option<FeedData>,
+}
+
+pub struct CachedFeeds {
+    conn: Connection,
+    warning: Option<String>,
+    known: Known,
+}
+
+// the rows as this process last read or wrote them. saving only writes what
+// changed since, so changes other processes made in the meantime are kept
+#[derive(Default)]
+struct Known {
+    feeds: HashMap<String, (String, Option<String>)>,
+    items: HashMap<ItemId, ItemColumns>,
+}
+// read_on, is_filtered, starred and a hash of data
+type ItemColumns = (Option<DateTime<Utc>>, bool, bool, u64);
 impl CachedFeeds {
-    pub fn init() {
+    pub fn open() -> Result<(Self, Vec<CachedFeed>), CacheError> {
         let proj = ProjectDirs::from("", "", PROJECT_NAME).unwrap();
-        fs::create_dir_all(proj.data_dir()).unwrap();
+        fs::create_dir_all(proj.data_dir())?;
         let path = proj.data_dir().join(CACHE_FILE);
-        if !path.exists() {
-            let _ = fs::File::create(path).unwrap();
+        let (mut cache, mut feeds) = match Self::open_at(&path) {
+            Ok((cache, feeds)) => {
+                cache.backup(&path, CONFIG.get().cache_backups)?;
+                (cache, feeds)
+            }
+            Err(e) if e.is_corruption() => Self::recover(&path, e, CONFIG.get().cache_backups)?,
+            Err(e) => return Err(e),
+        };
+
+        let legacy = proj.data_dir().join(LEGACY_CACHE_FILE);
+        if legacy.exists() {
+            match cache.import_legacy(&legacy) {
+                Ok(()) => {
+                    fs::rename(&legacy, with_suffix(&legacy, ".imported"))?;
+                    feeds = cache.load()?;
+                }
+                Err(e) => {
+                    fs::rename(&legacy, with_suffix(&legacy, ".invalid"))?;
+                    cache.warning = Some(format!("{e}, moved it to {LEGACY_CACHE_FILE}.invalid"));
+                }
+            }
         }
+        Ok((cache, feeds))
     }
-    pub fn save(feeds: &[Feed]) -> Result<(), Box<dyn std::error::Error>> {
-        let path = ProjectDirs::from("", "", PROJECT_NAME)
-            .unwrap()
-            .data_dir()
-            .join(CACHE_FILE);
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(false)
-            .open(path)?;
-
-        file.write_all(&bincode::serde::encode_to_vec(
-            feeds,
-            bincode::config::legacy(),
-        )?)?;
+    pub fn warning(&self) -> Option<&str> {
+        self.warning.as_deref()
+    }
+    // rows that no longer decode, e.g. after the models changed, are skipped
+    // instead of failing the whole cache
+    pub fn load(&mut self) -> Result<Vec<CachedFeed>, CacheError> {
+        let mut skipped = 0;
+        let mut known = Known::default();
+        let mut items: HashMap<String, Vec<Item>> = HashMap::new();
+        let mut stmt = self.conn.prepare(
+            "SELECT feed_url, data, read_on, is_filtered, starred FROM items
+             ORDER BY feed_url, posted DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, item_from_row(row, 1)?))
+        })?;
+        for row in rows {
+            match row? {
+                (url, Ok(item)) => {
+                    let data = serde_json::to_string(&item.data)?;
+                    let _ = known
+                        .items
+                        .insert(item.data.id.clone(), columns(&item, &data));
+                    items.entry(url).or_default().push(item)
+                }
+                (_, Err(_)) => skipped += 1,
+            }
+        }
+
+        let mut stmt = self.conn.prepare("SELECT url, state, data FROM feeds")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?;
+        let mut feeds = vec![];
+        for row in rows {
+            let (url, state, data) = row?;
+            let state = serde_json::from_str(&state).unwrap_or_else(|_| {
+                skipped += 1;
+                FeedState::default()
+            });
+            let mut data = data.and_then(|d| match serde_json::from_str::<FeedData>(&d) {
+                Ok(data) => Some(data),
+                Err(_) => {
+                    skipped += 1;
+                    None
+                }
+            });
+            if let Some(data) = &mut data {
+                data.items = items.remove(&url).unwrap_or_default();
+            }
+            let _ = known
+                .feeds
+                .insert(url.clone(), feed_columns(&state, data.as_ref())?);
+            feeds.push(CachedFeed {
+                id: FeedId(url),
+                state,
+                data,
+            });
+        }
+        if skipped > 0 {
+            self.warning = Some(format!("skipped {skipped} malformed cache entries"));
+        }
+        self.known = known;
+        Ok(feeds)
+    }
+    pub fn save_feeds(&mut self, feeds: &[&Feed]) -> Result<(), CacheError> {
+        let tx = self.conn.transaction()?;
+        for feed in feeds {
+            sync_feed(&tx, &mut self.known, feed)?;
+        }
+        tx.commit()?;
         Ok(())
     }
-    pub fn load() -> Result<Vec<Feed>, Box<dyn std::error::Error>> {
-        let path = ProjectDirs::from("", "", PROJECT_NAME)
-            .unwrap()
-            .data_dir()
-            .join(CACHE_FILE);
-
-        match fs::File::open(path) {
-            Ok(mut file) => {
-                let mut data = vec![];
-                let _ = file.read_to_end(&mut data)?;
-                if data.is_empty() {
-                    return Ok(Vec::new());
+    pub fn save_feed_state(&mut self, feed: &Feed) -> Result<(), CacheError> {
+        let state = serde_json::to_string(&feed.state)?;
+        let _ = self.conn.execute(
+            "UPDATE feeds SET state = ?2 WHERE url = ?1",
+            params![feed.id().0, state],
+        )?;
+        if let Some(known) = self.known.feeds.get_mut(&feed.id().0) {
+            known.0 = state;
+        }
+        Ok(())
+    }
+    pub fn delete_feed(&mut self, id: &FeedId) -> Result<(), CacheError> {
+        let _ = self
+            .conn
+            .execute("DELETE FROM feeds WHERE url = ?1", params![id.0])?;
+        let _ = self.known.feeds.remove(&id.0);
+        self.known.items.retain(|item, _| item.0 != id.0);
+        Ok(())
+    }
+    pub fn clear_items(&mut self, id: &FeedId) -> Result<(), CacheError> {
+        let _ = self.conn.execute(
+            "DELETE FROM items WHERE feed_url = ?1 AND starred = 0",
+            params![id.0],
+        )?;
+        self.known.items.retain(|item, _| item.0 != id.0);
+        Ok(())
+    }
+    pub fn set_read_on(
+        &mut self,
+        id: &ItemId,
+        read_on: Option<DateTime<Utc>>,
+    ) -> Result<(), CacheError> {
+        let _ = self.conn.execute(
+            "UPDATE items SET read_on = ?3 WHERE feed_url = ?1 AND id = ?2",
+            params![id.0, id.1, read_on],
+        )?;
+        if let Some(known) = self.known.items.get_mut(id) {
+            known.0 = read_on;
+        }
+        Ok(())
+    }
+    pub fn set_starred(&mut self, id: &ItemId, starred: bool) -> Result<(), CacheError> {
+        let _ = self.conn.execute(
+            "UPDATE items SET starred = ?3 WHERE feed_url = ?1 AND id = ?2",
+            params![id.0, id.1, starred],
+        )?;
+        if let Some(known) = self.known.items.get_mut(id) {
+            known.2 = starred;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn open_at(path: &Path) -> Result<(Self, Vec<CachedFeed>), CacheError> {
+        let mut conn = Connection::open(path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "FULL")?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let check: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+        if check != "ok" {
+            return Err(CacheError::Corrupted(check));
+        }
+        migrate(&mut conn)?;
+
+        let mut cache = Self {
+            conn,
+            warning: None,
+            known: Known::default(),
+        };
+        let feeds = cache.load()?;
+        Ok((cache, feeds))
+    }
+    fn recover(
+        path: &Path,
+        err: CacheError,
+        backups: usize,
+    ) -> Result<(Self, Vec<CachedFeed>), CacheError> {
+        let corrupt = with_suffix(
+            path,
+            &format!(".corrupt.{}", Utc::now().format("%Y%m%d%H%M%S")),
+        );
+        fs::rename(path, &corrupt)?;
+        remove_journal(path)?;
+        for backup in (1..=backups).map(|n| with_suffix(path, &format!(".{n}"))) {
+            if !backup.exists() {
+                continue;
+            }
+            let _ = fs::copy(&backup, path)?;
+            match Self::open_at(path) {
+                Ok((mut cache, feeds)) => {
+                    cache.warning = Some(format!(
+                        "{err}, restored {} (the broken cache was kept as {})",
+                        backup.display(),
+                        corrupt.display()
+                    ));
+                    return Ok((cache, feeds));
+                }
+                Err(_) => {
+                    fs::remove_file(path)?;
+                    remove_journal(path)?;
                 }
-                let data = bincode::serde::decode_from_slice(&data, bincode::config::legacy())?;
-                Ok(data.0)
             }
-            Err(e) => panic!("{e}"),
         }
+        let (mut cache, feeds) = Self::open_at(path)?;
+        cache.warning = Some(format!(
+            "{err}, no valid backup found (the broken cache was kept as {})",
+            corrupt.display()
+        ));
+        Ok((cache, feeds))
+    }
+    fn backup(&self, path: &Path, backups: usize) -> Result<(), CacheError> {
+        if backups == 0 {
+            return Ok(());
+        }
+        let newest = with_suffix(path, ".1");
+        let age = fs::metadata(&newest)
+            .and_then(|m| m.modified())
+            .map(|modified| modified.elapsed().unwrap_or_default());
+        if age.is_ok_and(|age| age < BACKUP_INTERVAL) {
+            return Ok(());
+        }
+
+        let tmp = with_suffix(path, ".tmp");
+        if tmp.exists() {
+            fs::remove_file(&tmp)?;
+        }
+        let _ = self
+            .conn
+            .execute("VACUUM INTO ?1", [tmp.to_string_lossy()])?;
+        fs::File::open(&tmp)?.sync_all()?;
+        for n in (1..backups).rev() {
+            let from = with_suffix(path, &format!(".{n}"));
+            if from.exists() {
+                fs::rename(from, with_suffix(path, &format!(".{}", n + 1)))?;
+            }
+        }
+        fs::rename(tmp, newest)?;
+        Ok(())
+    }
+    fn import_legacy(&mut self, path: &Path) -> Result<(), CacheError> {
+        let has_feeds: bool =
+            self.conn
+                .query_row("SELECT EXISTS (SELECT 1 FROM feeds)", [], |row| row.get(0))?;
+        let data = fs::read(path)?;
+        if has_feeds || data.is_empty() {
+            return Ok(());
+        }
+        let (feeds, _): (Vec<legacy::Feed>, _) =
+            bincode::serde::decode_from_slice(&data, bincode::config::legacy())?;
+        let feeds = feeds.into_iter().map(Feed::from).collect::<Vec<_>>();
+        self.save_feeds(&feeds.iter().collect::<Vec<_>>())
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+fn remove_journal(path: &Path) -> Result<(), CacheError> {
+    for journal in [with_suffix(path, "-wal"), with_suffix(path, "-shm")] {
+        if journal.exists() {
+            fs::remove_file(journal)?;
+        }
+    }
+    Ok(())
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), CacheError> {
+    let magic: i32 = conn.pragma_query_value(None, "application_id", |row| row.get(0))?;
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if magic != MAGIC && (magic != 0 || version != 0) {
+        return Err(CacheError::Corrupted(format!(
+            "unknown application id {magic:#x}"
+        )));
+    }
+    if version > MIGRATIONS.len() {
+        return Err(CacheError::Version(version));
+    }
+    if magic == MAGIC && version == MIGRATIONS.len() {
+        return Ok(());
+    }
+
+    let tx = conn.transaction()?;
+    for migration in &MIGRATIONS[version..] {
+        migration(&tx)?;
+    }
+    tx.pragma_update(None, "application_id", MAGIC)?;
+    tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+    tx.commit()?;
+    Ok(())
+}
+
+// caches created before versioning already have this schema, hence the IF NOT EXISTS
+fn migrate_v1(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS feeds (
+            url TEXT PRIMARY KEY,
+            state TEXT NOT NULL,
+            data TEXT
+        );
+        CREATE TABLE IF NOT EXISTS items (
+            feed_url TEXT NOT NULL REFERENCES feeds(url) ON DELETE CASCADE,
+            id TEXT NOT NULL,
+            title TEXT,
+            posted TEXT,
+            read_on TEXT,
+            is_filtered INTEGER NOT NULL DEFAULT 0,
+            data TEXT NOT NULL,
+            PRIMARY KEY (feed_url, id)
+        );
+        CREATE INDEX IF NOT EXISTS items_read_on ON items(feed_url, read_on);
+        CREATE INDEX IF NOT EXISTS items_posted ON items(feed_url, posted);
+        ",
+    )
+}
+
+fn migrate_v2(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        ALTER TABLE items ADD COLUMN starred INTEGER NOT NULL DEFAULT 0;
+        CREATE INDEX items_starred ON items(feed_url, starred);
+        ",
+    )
+}
+
+fn item_from_row(row: &rusqlite::Row, offset: usize) -> rusqlite::Result<Result<Item, CacheError>> {
+    let data: String = row.get(offset)?;
+    let state = ItemState {
+        read_on: row.get(offset + 1)?,
+        is_filtered: row.get(offset + 2)?,
+        starred: row.get(offset + 3)?,
+        effects: RuleEffects::default(),
+    };
+    Ok(serde_json::from_str::<ItemData>(&data)
+        .map(|data| Item { data, state })
+        .map_err(CacheError::from))
+}
+
+fn columns(item: &Item, data: &str) -> ItemColumns {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    (
+        item.state.read_on,
+        item.state.is_filtered,
+        item.state.starred,
+        hasher.finish(),
+    )
+}
+
+fn feed_columns(
+    state: &FeedState,
+    data: Option<&FeedData>,
+) -> Result<(String, Option<String>), CacheError> {
+    Ok((
+        serde_json::to_string(state)?,
+        data.map(serde_json::to_string).transpose()?,
+    ))
+}
+
+fn sync_feed(tx: &Transaction, known: &mut Known, feed: &Feed) -> Result<(), CacheError> {
+    let url = &feed.id().0;
+    let (state, data) = feed_columns(&feed.state, feed.data.as_ref())?;
+    match known.feeds.get(url) {
+        None => {
+            let _ = tx.execute(
+                "INSERT INTO feeds (url, state, data) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(url) DO UPDATE SET state = excluded.state, data = excluded.data",
+                params![url, state, data],
+            )?;
+        }
+        Some((known_state, known_data)) => {
+            let _ = tx.execute(
+                "UPDATE feeds SET
+                     state = CASE WHEN ?2 THEN ?3 ELSE state END,
+                     data = CASE WHEN ?4 THEN ?5 ELSE data END
+                 WHERE url = ?1 AND (?2 OR ?4)",
+                params![url, *known_state != state, state, *known_data != data, data],
+            )?;
+        }
+    }
+    let _ = known.feeds.insert(url.clone(), (state, data));
+
+    let empty = vec![];
+    let items = feed.items().unwrap_or(&empty);
+    // an item another process stored first keeps its state
+    let mut insert = tx.prepare(
+        "INSERT INTO items (feed_url, id, title, posted, read_on, is_filtered, starred, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT(feed_url, id) DO NOTHING",
+    )?;
+    let mut update = tx.prepare(
+        "UPDATE items SET
+             read_on = CASE WHEN ?3 THEN ?4 ELSE read_on END,
+             is_filtered = CASE WHEN ?5 THEN ?6 ELSE is_filtered END,
+             starred = CASE WHEN ?7 THEN ?8 ELSE starred END,
+             title = CASE WHEN ?9 THEN ?10 ELSE title END,
+             posted = CASE WHEN ?9 THEN ?11 ELSE posted END,
+             data = CASE WHEN ?9 THEN ?12 ELSE data END
+         WHERE feed_url = ?1 AND id = ?2",
+    )?;
+    for item in items {
+        let (url, id) = (&item.data.id.0, &item.data.id.1);
+        let data = serde_json::to_string(&item.data)?;
+        let new = columns(item, &data);
+        let (read_on, is_filtered, starred, _) = new;
+        match known.items.get(&item.data.id) {
+            None => {
+                let _ = insert.execute(params![
+                    url,
+                    id,
+                    item.data.title,
+                    item.data.posted,
+                    read_on,
+                    is_filtered,
+                    starred,
+                    data,
+                ])?;
+            }
+            Some(old) if *old != new => {
+                let _ = update.execute(params![
+                    url,
+                    id,
+                    old.0 != read_on,
+                    read_on,
+                    old.1 != is_filtered,
+                    is_filtered,
+                    old.2 != starred,
+                    starred,
+                    old.3 != new.3,
+                    item.data.title,
+                    item.data.posted,
+                    data,
+                ])?;
+            }
+            Some(_) => continue,
+        }
+        let _ = known.items.insert(item.data.id.clone(), new);
+    }
+
+    // other processes may have stored items this one has not loaded, so
+    // only the ones past the feed limit are pruned, as truncate_items does
+    let _ = tx.execute(
+        "DELETE FROM items WHERE feed_url = ?1 AND starred = 0 AND id NOT IN (
+             SELECT id FROM items WHERE feed_url = ?1 AND starred = 0
+             ORDER BY posted DESC LIMIT ?2
+         )",
+        params![feed.id().0, feed.conf.max_items],
+    )?;
+    Ok(())
+}
+
+// layout of feeds.bin as written by the bincode cache, kept only for the import
+mod legacy {
+    use chrono::{DateTime, Utc};
+    use serde::Deserialize;
+
+    use crate::config::{self, FeedSource};
+    use crate::models::{self, FeedId, FeedType, ItemId, Link, MediaObject};
+
+    #[derive(Deserialize)]
+    pub struct Feed {
+        conf: Source,
+        state: FeedState,
+        data: Option<FeedData>,
+    }
+    #[derive(Deserialize)]
+    struct Source {
+        url: FeedId,
+        tags: Vec<String>,
+        manual_update: bool,
+        notify: bool,
+        filter: Option<FeedFilter>,
+        max_items: u32,
+    }
+    #[derive(Deserialize)]
+    struct FeedFilter {
+        pattern: String,
+        invert: bool,
+        case_insensitive: bool,
+    }
+    #[derive(Deserialize)]
+    struct FeedState {
+        latest_item_date: Option<DateTime<Utc>>,
+        hits: usize,
+        is_recent: bool,
+        exchanged_bytes: usize,
+    }
+    #[derive(Deserialize)]
+    struct FeedData {
+        feed_type: FeedType,
+        title: String,
+        items: Vec<Item>,
+        published: Option<DateTime<Utc>>,
+        updated: Option<DateTime<Utc>>,
+        links: Vec<Link>,
+        authors: Vec<String>,
+        description: Option<String>,
+        categories: Vec<String>,
+        language: Option<String>,
+    }
+    #[derive(Deserialize)]
+    struct Item {
+        data: ItemData,
+        state: ItemState,
+    }
+    #[derive(Deserialize)]
+    struct ItemState {
+        read_on: Option<DateTime<Utc>>,
+        is_filtered: bool,
+    }
+    #[derive(Deserialize)]
+    struct ItemData {
+        id: ItemId,
+        title: Option<String>,
+        content: Option<String>,
+        summary: Option<String>,
+        media: Vec<MediaObject>,
+        posted: Option<DateTime<Utc>>,
+        links: Vec<Link>,
+    }
+
+    impl From<Feed> for models::Feed {
+        fn from(feed: Feed) -> Self {
+            Self {
+                conf: FeedSource {
+                    url: feed.conf.url,
+                    tags: feed.conf.tags,
+                    manual_update: feed.conf.manual_update,
+                    notify: feed.conf.notify,
+                    filter: feed.conf.filter.and_then(|f| {
+                        config::FeedFilter::new(f.pattern, f.invert, f.case_insensitive).ok()
+                    }),
+                    rules: vec![],
+                    max_items: feed.conf.max_items,
+                    timeout: None,
+                    update_interval: None,
+                },
+                state: models::FeedState {
+                    latest_item_date: feed.state.latest_item_date,
+                    hits: feed.state.hits,
+                    is_recent: feed.state.is_recent,
+                    exchanged_bytes: feed.state.exchanged_bytes,
+                    ..Default::default()
+                },
+                data: feed.data.map(|d| models::FeedData {
+                    feed_type: d.feed_type,
+                    title: d.title,
+                    items: d.items.into_iter().map(models::Item::from).collect(),
+                    published: d.published,
+                    updated: d.updated,
+                    links: d.links,
+                    authors: d.authors,
+                    description: d.description,
+                    categories: d.categories,
+                    language: d.language,
+                    ttl: None,
+                }),
+            }
+        }
+    }
+    impl From<Item> for models::Item {
+        fn from(item: Item) -> Self {
+            Self {
+                data: models::ItemData {
+                    id: item.data.id,
+                    title: item.data.title,
+                    content: item.data.content,
+                    summary: item.data.summary,
+                    authors: vec![],
+                    categories: vec![],
+                    media: item.data.media,
+                    posted: item.data.posted,
+                    links: item.data.links,
+                },
+                state: models::ItemState {
+                    read_on: item.state.read_on,
+                    is_filtered: item.state.is_filtered,
+                    starred: false,
+                    effects: models::RuleEffects::default(),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::config::FeedSource;
+    use crate::models::{FeedType, Link, MediaObject};
+
+    const URL: &str = "https://example.com/feed";
+
+    fn temp_cache(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rrss-cache-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(CACHE_FILE)
+    }
+    fn date(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap()
+    }
+    fn item(n: u32, starred: bool) -> Item {
+        Item {
+            data: ItemData {
+                id: ItemId(URL.to_string(), format!("item-{n}")),
+                title: Some(format!("item {n}")),
+                content: None,
+                summary: None,
+                authors: vec![],
+                categories: vec![],
+                media: vec![],
+                posted: Some(date(n)),
+                links: vec![],
+            },
+            state: ItemState {
+                read_on: None,
+                is_filtered: false,
+                starred,
+                effects: RuleEffects::default(),
+            },
+        }
+    }
+    fn feed(items: Vec<Item>, max_items: u32) -> Feed {
+        let mut feed = Feed::new(FeedSource::new(FeedId(URL.to_string()), vec![]));
+        feed.conf.max_items = max_items;
+        feed.data = Some(FeedData {
+            title: "feed".to_string(),
+            items,
+            ..Default::default()
+        });
+        feed
+    }
+    fn item_ids(feeds: &[CachedFeed]) -> Vec<String> {
+        let items = feeds[0].data.as_ref().unwrap().items.iter();
+        items.map(|i| i.data.id.1.clone()).collect()
+    }
+    fn pragma(conn: &Connection, name: &str) -> i64 {
+        conn.pragma_query_value(None, name, |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn migrates_an_unversioned_cache() {
+        let path = temp_cache("migrate");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "
+                CREATE TABLE feeds (url TEXT PRIMARY KEY, state TEXT NOT NULL, data TEXT);
+                CREATE TABLE items (
+                    feed_url TEXT NOT NULL REFERENCES feeds(url) ON DELETE CASCADE,
+                    id TEXT NOT NULL,
+                    title TEXT,
+                    posted TEXT,
+                    read_on TEXT,
+                    is_filtered INTEGER NOT NULL DEFAULT 0,
+                    data TEXT NOT NULL,
+                    PRIMARY KEY (feed_url, id)
+                );
+                ",
+            )
+            .unwrap();
+            let item = item(1, false);
+            let _ = conn
+                .execute(
+                    "INSERT INTO feeds (url, state, data) VALUES (?1, '{}', ?2)",
+                    params![URL, r#"{"title":"feed"}"#],
+                )
+                .unwrap();
+            let _ = conn
+                .execute(
+                    "INSERT INTO items (feed_url, id, data) VALUES (?1, ?2, ?3)",
+                    params![
+                        URL,
+                        item.data.id.1,
+                        serde_json::to_string(&item.data).unwrap()
+                    ],
+                )
+                .unwrap();
+        }
+
+        let (cache, feeds) = CachedFeeds::open_at(&path).unwrap();
+        assert_eq!(pragma(&cache.conn, "application_id"), MAGIC as i64);
+        assert_eq!(pragma(&cache.conn, "user_version"), MIGRATIONS.len() as i64);
+        assert_eq!(item_ids(&feeds), vec!["item-1"]);
+        assert!(!feeds[0].data.as_ref().unwrap().items[0].state.starred);
+        assert!(cache.warning().is_none());
+    }
+
+    #[test]
+    fn rejects_a_foreign_database() {
+        let path = temp_cache("foreign");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.pragma_update(None, "application_id", 0x1234).unwrap();
+            conn.execute_batch("CREATE TABLE other (x INTEGER);")
+                .unwrap();
+        }
+        match CachedFeeds::open_at(&path) {
+            Err(e @ CacheError::Corrupted(_)) => assert!(e.is_corruption()),
+            Err(e) => panic!("unexpected error: {e}"),
+            Ok(_) => panic!("a foreign database was opened"),
+        }
+    }
+
+    #[test]
+    fn skips_rows_that_no_longer_decode() {
+        let path = temp_cache("drift");
+        let (mut cache, _) = CachedFeeds::open_at(&path).unwrap();
+        cache
+            .save_feeds(&[&feed(vec![item(1, false), item(2, false)], 10)])
+            .unwrap();
+        let _ = cache
+            .conn
+            .execute("UPDATE items SET data = '{}' WHERE id = 'item-1'", [])
+            .unwrap();
+        let _ = cache
+            .conn
+            .execute("UPDATE feeds SET data = '{\"title\": \"feed\"}'", [])
+            .unwrap();
+        drop(cache);
+
+        let (cache, feeds) = CachedFeeds::open_at(&path).unwrap();
+        assert_eq!(item_ids(&feeds), vec!["item-2"]);
+        assert!(cache.warning().is_some());
+    }
+
+    #[test]
+    fn imports_the_legacy_cache() {
+        let path = temp_cache("legacy");
+        let legacy = path.with_file_name(LEGACY_CACHE_FILE);
+        // same field order as the legacy structs, bincode does not encode names
+        let item = (
+            (
+                ItemId(URL.to_string(), "old".to_string()),
+                Some("old item"),
+                None::<String>,
+                None::<String>,
+                Vec::<MediaObject>::new(),
+                Some(date(1)),
+                Vec::<Link>::new(),
+            ),
+            (Some(date(2)), false),
+        );
+        let data = (
+            FeedType(None),
+            "old feed",
+            vec![item],
+            None::<DateTime<Utc>>,
+            None::<DateTime<Utc>>,
+            Vec::<Link>::new(),
+            Vec::<String>::new(),
+            None::<String>,
+            Vec::<String>::new(),
+            None::<String>,
+        );
+        let feed = (
+            (
+                FeedId(URL.to_string()),
+                vec!["tag"],
+                false,
+                true,
+                Some(("rust", true, false)),
+                50u32,
+            ),
+            (Some(date(1)), 3usize, false, 1024usize),
+            Some(data),
+        );
+        let bytes = bincode::serde::encode_to_vec(vec![feed], bincode::config::legacy()).unwrap();
+        fs::write(&legacy, bytes).unwrap();
+
+        let (mut cache, _) = CachedFeeds::open_at(&path).unwrap();
+        cache.import_legacy(&legacy).unwrap();
+        let feeds = cache.load().unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].id.0, URL);
+        assert_eq!(feeds[0].state.hits, 3);
+        let data = feeds[0].data.as_ref().unwrap();
+        assert_eq!(data.title, "old feed");
+        assert_eq!(data.items.len(), 1);
+        assert_eq!(data.items[0].state.read_on, Some(date(2)));
+
+        // an already populated cache is left alone
+        cache.import_legacy(&legacy).unwrap();
+        assert_eq!(item_ids(&cache.load().unwrap()), vec!["old"]);
+    }
+
+    #[test]
+    fn sync_upserts_and_keeps_starred_items() {
+        let path = temp_cache("sync");
+        let (mut cache, _) = CachedFeeds::open_at(&path).unwrap();
+        let mut feed = feed((1..=4).map(|n| item(n, n == 1)).collect(), 2);
+        cache.save_feeds(&[&feed]).unwrap();
+        // only the two newest unstarred items and the starred one are kept
+        let feeds = cache.load().unwrap();
+        assert_eq!(item_ids(&feeds), vec!["item-4", "item-3", "item-1"]);
+
+        let items = feed.items_mut().unwrap();
+        items.retain(|i| i.data.id.1 != "item-3");
+        let read = items.iter_mut().find(|i| i.data.id.1 == "item-4").unwrap();
+        read.state.read_on = Some(date(5));
+        items.push(item(5, false));
+        cache.save_feeds(&[&feed]).unwrap();
+        let feeds = cache.load().unwrap();
+        assert_eq!(item_ids(&feeds), vec!["item-5", "item-4", "item-1"]);
+        let items = &feeds[0].data.as_ref().unwrap().items;
+        assert_eq!(items[1].state.read_on, Some(date(5)));
+        assert!(items[2].state.starred);
+    }
+
+    #[test]
+    fn sync_keeps_changes_from_other_processes() {
+        let path = temp_cache("concurrent");
+        let (mut a, _) = CachedFeeds::open_at(&path).unwrap();
+        let mut feed = feed(vec![item(1, false), item(2, false)], 10);
+        a.save_feeds(&[&feed]).unwrap();
+
+        let (mut b, _) = CachedFeeds::open_at(&path).unwrap();
+        let first = ItemId(URL.to_string(), "item-1".to_string());
+        b.set_read_on(&first, Some(date(3))).unwrap();
+        b.set_starred(&first, true).unwrap();
+        let mut state = feed.state.clone();
+        state.hits = 7;
+        b.save_feed_state(&Feed {
+            state,
+            ..feed.clone()
+        })
+        .unwrap();
+
+        // `a` still has the old copy of item-1 and only changed item-2
+        let items = feed.items_mut().unwrap();
+        let second = items.iter_mut().find(|i| i.data.id.1 == "item-2").unwrap();
+        second.state.read_on = Some(date(4));
+        a.save_feeds(&[&feed]).unwrap();
+
+        let feeds = a.load().unwrap();
+        assert_eq!(feeds[0].state.hits, 7);
+        let items = &feeds[0].data.as_ref().unwrap().items;
+        assert_eq!(item_ids(&feeds), vec!["item-2", "item-1"]);
+        assert_eq!(items[0].state.read_on, Some(date(4)));
+        assert_eq!(items[1].state.read_on, Some(date(3)));
+        assert!(items[1].state.starred);
+    }
+
+    #[test]
+    fn backups_rotate() {
+        let path = temp_cache("backup");
+        let (cache, _) = CachedFeeds::open_at(&path).unwrap();
+        let backup = |n| with_suffix(&path, &format!(".{n}"));
+        let age = |n| {
+            let file = fs::File::options().write(true).open(backup(n)).unwrap();
+            file.set_modified(SystemTime::now() - BACKUP_INTERVAL * 2)
+                .unwrap();
+        };
+
+        cache.backup(&path, 2).unwrap();
+        assert!(backup(1).exists());
+        assert!(!backup(2).exists());
+        // a recent backup is not replaced
+        cache.backup(&path, 2).unwrap();
+        assert!(!backup(2).exists());
+
+        age(1);
+        cache.backup(&path, 2).unwrap();
+        assert!(backup(2).exists());
+        age(1);
+        cache.backup(&path, 2).unwrap();
+        assert!(!backup(3).exists());
+        assert!(!with_suffix(&path, ".tmp").exists());
+    }
+
+    #[test]
+    fn recovers_from_a_backup() {
+        let path = temp_cache("recover");
+        {
+            let (mut cache, _) = CachedFeeds::open_at(&path).unwrap();
+            cache.save_feeds(&[&feed(vec![item(1, true)], 10)]).unwrap();
+            cache.backup(&path, 2).unwrap();
+        }
+        remove_journal(&path).unwrap();
+        fs::write(&path, vec![0x42; 4096]).unwrap();
+
+        let err = match CachedFeeds::open_at(&path) {
+            Err(e) if e.is_corruption() => e,
+            Err(e) => panic!("unexpected error: {e}"),
+            Ok(_) => panic!("a broken cache was opened"),
+        };
+        let (cache, feeds) = CachedFeeds::recover(&path, err, 2).unwrap();
+        assert_eq!(item_ids(&feeds), vec!["item-1"]);
+        assert!(cache.warning().is_some());
+        let kept = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().contains(".corrupt."))
+            .count();
+        assert_eq!(kept, 1);
     }
 }
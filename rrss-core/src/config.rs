@@ -1,14 +1,34 @@
+use std::collections::BTreeMap;
+use std::fs;
 use std::hash::Hash;
+use std::io::ErrorKind;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 
+use directories::ProjectDirs;
 use itertools::Itertools;
-use opml::OPML;
 use ratatui::style::Color;
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
+use serde_spanned::Spanned;
+use toml_edit::ser::ValueSerializer;
+use toml_edit::{value, Array, ArrayOfTables, DocumentMut, InlineTable, Item, Table};
+use ureq::http::Uri;
 
-use crate::models::{Feed, FeedId, FeedState};
+use crate::cache::CachedFeed;
+use crate::diagnostics::{Problem, TomlFile};
+use crate::error::ConfigError;
+use crate::filter::Filter;
+use crate::globals::{CONFIG_FILE, PROJECT_NAME, SOURCES_FILE};
+use crate::keybinds::{check_command, Keybind, PartialKeys};
+use crate::models::{self, Feed, FeedId, FeedType};
+use crate::opml::{Opml, Outline};
+use crate::sorter::Sorter;
 
 #[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct PartialTheme {
     fg_header_color: Option<String>,
     fg_selected_color: Option<String>,
@@ -16,6 +36,7 @@ pub struct PartialTheme {
     fg_unread_color: Option<String>,
     fg_filtered_color: Option<String>,
     fg_item_header: Option<String>,
+    fg_highlight_color: Option<String>,
 
     bg_header_color: Option<String>,
     bg_selected_color: Option<String>,
@@ -31,6 +52,7 @@ pub struct PartialTheme {
     date_format: Option<String>,
     unread_marker: Option<char>,
     read_marker: Option<char>,
+    starred_marker: Option<char>,
 }
 #[derive(Debug)]
 pub struct Theme {
@@ -40,6 +62,7 @@ pub struct Theme {
     pub fg_unread_color: Color,
     pub fg_filtered_color: Color,
     pub fg_item_header: Color,
+    pub fg_highlight_color: Color,
 
     pub bg_header_color: Color,
     pub bg_selected_color: Color,
@@ -55,96 +78,393 @@ pub struct Theme {
     pub date_format: String,
     pub unread_marker: char,
     pub read_marker: char,
+    pub starred_marker: char,
 }
-impl From<PartialTheme> for Theme {
-    fn from(val: PartialTheme) -> Self {
+impl Theme {
+    fn build(val: PartialTheme, problems: &mut Vec<Problem>) -> Self {
         Self {
-            fg_header_color: Color::from_str(&val.fg_header_color.unwrap_or("blue".to_string()))
-                .unwrap(),
-            fg_selected_color: Color::from_str(&val.fg_selected_color.unwrap_or("white".into()))
-                .unwrap(),
-            fg_normal_color: Color::from_str(&val.fg_normal_color.unwrap_or("white".into()))
-                .unwrap(),
-            fg_unread_color: Color::from_str(&val.fg_unread_color.unwrap_or("yellow".into()))
-                .unwrap(),
-            fg_filtered_color: Color::from_str(&val.fg_filtered_color.unwrap_or("darkgray".into()))
-                .unwrap(),
-            fg_item_header: Color::from_str(&val.fg_item_header.unwrap_or("white".into())).unwrap(),
-
-            bg_header_color: Color::from_str(&val.bg_header_color.unwrap_or("black".into()))
-                .unwrap(),
-            bg_selected_color: Color::from_str(&val.bg_selected_color.unwrap_or("darkgray".into()))
-                .unwrap(),
-            bg_normal_color: Color::from_str(&val.bg_normal_color.unwrap_or("black".into()))
-                .unwrap(),
-            bg_unread_color: Color::from_str(&val.bg_unread_color.unwrap_or("black".into()))
-                .unwrap(),
-            bg_filterd_color: Color::from_str(&val.bg_filterd_color.unwrap_or("black".into()))
-                .unwrap(),
-            bg_item_header: Color::from_str(&val.bg_item_header.unwrap_or("blue".into())).unwrap(),
-
-            border_color: Color::from_str(&val.border_color.unwrap_or("yellow".into())).unwrap(),
+            fg_header_color: color(
+                val.fg_header_color,
+                Color::Blue,
+                "fg_header_color",
+                problems,
+            ),
+            fg_selected_color: color(
+                val.fg_selected_color,
+                Color::White,
+                "fg_selected_color",
+                problems,
+            ),
+            fg_normal_color: color(
+                val.fg_normal_color,
+                Color::White,
+                "fg_normal_color",
+                problems,
+            ),
+            fg_unread_color: color(
+                val.fg_unread_color,
+                Color::Yellow,
+                "fg_unread_color",
+                problems,
+            ),
+            fg_filtered_color: color(
+                val.fg_filtered_color,
+                Color::DarkGray,
+                "fg_filtered_color",
+                problems,
+            ),
+            fg_item_header: color(val.fg_item_header, Color::White, "fg_item_header", problems),
+            fg_highlight_color: color(
+                val.fg_highlight_color,
+                Color::Green,
+                "fg_highlight_color",
+                problems,
+            ),
+
+            bg_header_color: color(
+                val.bg_header_color,
+                Color::Black,
+                "bg_header_color",
+                problems,
+            ),
+            bg_selected_color: color(
+                val.bg_selected_color,
+                Color::DarkGray,
+                "bg_selected_color",
+                problems,
+            ),
+            bg_normal_color: color(
+                val.bg_normal_color,
+                Color::Black,
+                "bg_normal_color",
+                problems,
+            ),
+            bg_unread_color: color(
+                val.bg_unread_color,
+                Color::Black,
+                "bg_unread_color",
+                problems,
+            ),
+            bg_filterd_color: color(
+                val.bg_filterd_color,
+                Color::Black,
+                "bg_filterd_color",
+                problems,
+            ),
+            bg_item_header: color(val.bg_item_header, Color::Blue, "bg_item_header", problems),
+
+            border_color: color(val.border_color, Color::Yellow, "border_color", problems),
             borders: val.borders.unwrap_or(true),
             rounded_borders: val.rounded_borders.unwrap_or(false),
             date_format: val.date_format.unwrap_or_else(|| "%Y-%m-%d".to_string()),
             unread_marker: val.unread_marker.unwrap_or('*'),
             read_marker: val.read_marker.unwrap_or(' '),
+            starred_marker: val.starred_marker.unwrap_or('+'),
             column_spacing: val.column_spacing.unwrap_or(1),
         }
     }
 }
 
-#[derive(Deserialize)]
+fn color(val: Option<String>, default: Color, key: &str, problems: &mut Vec<Problem>) -> Color {
+    match val {
+        Some(val) => Color::from_str(&val).unwrap_or_else(|_| {
+            let key = format!("theme.{key}");
+            problems.push(Problem::new(None, &key, format!("invalid color '{val}'")));
+            default
+        }),
+        None => default,
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct PartialConfig {
     relative_time_threshold: Option<u32>,
     max_concurrency: Option<usize>,
+    fetch_timeout: Option<u64>,
+    update_deadline: Option<u64>,
+    failure_threshold: Option<u32>,
+    auto_update: Option<bool>,
+    update_interval: Option<u64>,
+    cache_backups: Option<usize>,
+    watch_config: Option<bool>,
+    mark_read_on_open: Option<bool>,
     theme: Option<PartialTheme>,
+    rules: Option<Vec<Spanned<PartialRule>>>,
+    keybinds: Option<BTreeMap<String, BTreeMap<String, PartialKeys>>>,
 }
 pub struct Config {
     pub relative_time_threshold: u32,
     pub max_concurrency: usize,
+    pub fetch_timeout: Duration,
+    pub update_deadline: Duration,
+    pub failure_threshold: u32,
+    pub auto_update: bool,
+    pub update_interval: u64,
+    pub cache_backups: usize,
+    pub watch_config: bool,
+    pub mark_read_on_open: bool,
     pub theme: Theme,
+    pub rules: Vec<Rule>,
+    pub keybinds: Vec<Keybind>,
 }
-impl From<PartialConfig> for Config {
-    fn from(val: PartialConfig) -> Self {
+impl Config {
+    pub fn load() -> (Self, Vec<ConfigError>) {
+        let (file, val) = TomlFile::parse::<PartialConfig>(CONFIG_FILE);
+        let mut problems = vec![];
+        let config = Self::build(val, &mut problems);
+        (config, file.resolve(problems))
+    }
+    fn build(val: PartialConfig, problems: &mut Vec<Problem>) -> Self {
         Self {
             max_concurrency: val.max_concurrency.unwrap_or(5),
+            fetch_timeout: Duration::from_secs(val.fetch_timeout.unwrap_or(30)),
+            update_deadline: Duration::from_secs(val.update_deadline.unwrap_or(300)),
+            failure_threshold: val.failure_threshold.unwrap_or(5),
+            auto_update: val.auto_update.unwrap_or(false),
+            update_interval: val.update_interval.unwrap_or(60),
+            cache_backups: val.cache_backups.unwrap_or(3),
+            watch_config: val.watch_config.unwrap_or(false),
+            mark_read_on_open: val.mark_read_on_open.unwrap_or(true),
             relative_time_threshold: val.relative_time_threshold.unwrap_or(3),
-            theme: Theme::from(val.theme.unwrap_or_default()),
+            theme: Theme::build(val.theme.unwrap_or_default(), problems),
+            rules: rules(val.rules.unwrap_or_default(), problems),
+            keybinds: keybinds(val.keybinds.unwrap_or_default(), problems),
+        }
+    }
+}
+
+fn keybinds(
+    val: BTreeMap<String, BTreeMap<String, PartialKeys>>,
+    problems: &mut Vec<Problem>,
+) -> Vec<Keybind> {
+    let mut keybinds = vec![];
+    for (view, commands) in val {
+        for (command, keys) in commands {
+            let key = format!("keybinds.{view}.{command}");
+            if let Err(e) = check_command(&view, &command) {
+                problems.push(Problem::new(None, &key, e));
+                continue;
+            }
+            match keys.parse() {
+                Ok(keys) => keybinds.push(Keybind {
+                    view: view.clone(),
+                    command,
+                    keys,
+                }),
+                Err(e) => problems.push(Problem::new(None, &key, e)),
+            }
         }
     }
+    keybinds
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PartialFeedFilter {
     pattern: String,
     invert: Option<bool>,
     case_insensitive: Option<bool>,
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "PartialFeedFilter", into = "PartialFeedFilter")]
 pub struct FeedFilter {
     pub pattern: String,
     pub invert: bool,
     pub case_insensitive: bool,
+    pub regex: Regex,
+}
+impl FeedFilter {
+    pub fn new(
+        pattern: String,
+        invert: bool,
+        case_insensitive: bool,
+    ) -> Result<Self, regex::Error> {
+        Ok(Self {
+            regex: RegexBuilder::new(&pattern)
+                .case_insensitive(case_insensitive)
+                .build()?,
+            pattern,
+            invert,
+            case_insensitive,
+        })
+    }
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
 }
-impl From<PartialFeedFilter> for FeedFilter {
-    fn from(value: PartialFeedFilter) -> Self {
+impl TryFrom<PartialFeedFilter> for FeedFilter {
+    type Error = regex::Error;
+    fn try_from(value: PartialFeedFilter) -> Result<Self, Self::Error> {
+        Self::new(
+            value.pattern,
+            value.invert.unwrap_or(false),
+            value.case_insensitive.unwrap_or(false),
+        )
+    }
+}
+impl From<FeedFilter> for PartialFeedFilter {
+    fn from(value: FeedFilter) -> Self {
         Self {
             pattern: value.pattern,
-            invert: value.invert.unwrap_or(false),
-            case_insensitive: value.case_insensitive.unwrap_or(false),
+            invert: Some(value.invert),
+            case_insensitive: Some(value.case_insensitive),
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PartialRule {
+    name: Option<String>,
+    #[serde(rename = "match")]
+    field: Option<String>,
+    pattern: String,
+    case_insensitive: Option<bool>,
+    invert: Option<bool>,
+    actions: Vec<String>,
+}
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,
+    pub field: RuleField,
+    pub pattern: Regex,
+    pub case_insensitive: bool,
+    pub invert: bool,
+    pub actions: Vec<RuleAction>,
+}
+impl TryFrom<PartialRule> for Rule {
+    type Error = String;
+    fn try_from(val: PartialRule) -> Result<Self, Self::Error> {
+        let field = match val.field.as_deref().unwrap_or("title") {
+            "title" => RuleField::Title,
+            "content" => RuleField::Content,
+            "author" => RuleField::Author,
+            "category" => RuleField::Category,
+            "domain" => RuleField::Domain,
+            field => return Err(format!("unknown match '{field}'")),
+        };
+        let case_insensitive = val.case_insensitive.unwrap_or(false);
+        let pattern = RegexBuilder::new(&val.pattern)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let actions = val
+            .actions
+            .iter()
+            .map(|a| RuleAction::from_str(a))
+            .collect::<Result<Vec<_>, _>>()?;
+        if actions.is_empty() {
+            return Err("no actions".into());
+        }
+        Ok(Self {
+            name: val
+                .name
+                .unwrap_or_else(|| format!("{}:/{}/", field, val.pattern)),
+            field,
+            pattern,
+            case_insensitive,
+            invert: val.invert.unwrap_or(false),
+            actions,
+        })
+    }
+}
+impl From<&Rule> for PartialRule {
+    fn from(rule: &Rule) -> Self {
+        Self {
+            name: Some(rule.name.clone()),
+            field: Some(rule.field.to_string()),
+            pattern: rule.pattern.as_str().to_string(),
+            case_insensitive: Some(rule.case_insensitive),
+            invert: Some(rule.invert),
+            actions: rule.actions.iter().map(RuleAction::to_string).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuleField {
+    Title,
+    Content,
+    Author,
+    Category,
+    Domain,
+}
+impl std::fmt::Display for RuleField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleField::Title => write!(f, "title"),
+            RuleField::Content => write!(f, "content"),
+            RuleField::Author => write!(f, "author"),
+            RuleField::Category => write!(f, "category"),
+            RuleField::Domain => write!(f, "domain"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleAction {
+    Read,
+    Hide,
+    Highlight(Option<Color>),
+    Tag(String),
+    Notify,
+}
+impl FromStr for RuleAction {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "read" => Ok(RuleAction::Read),
+            None if s == "hide" => Ok(RuleAction::Hide),
+            None if s == "highlight" => Ok(RuleAction::Highlight(None)),
+            None if s == "notify" => Ok(RuleAction::Notify),
+            Some(("highlight", color)) => Color::from_str(color)
+                .map(|c| RuleAction::Highlight(Some(c)))
+                .map_err(|_| format!("invalid color '{color}'")),
+            Some(("tag", tag)) if !tag.trim().is_empty() => Ok(RuleAction::Tag(tag.trim().into())),
+            _ => Err(format!("unknown action '{s}'")),
+        }
+    }
+}
+impl std::fmt::Display for RuleAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleAction::Read => write!(f, "read"),
+            RuleAction::Hide => write!(f, "hide"),
+            RuleAction::Highlight(None) => write!(f, "highlight"),
+            RuleAction::Highlight(Some(c)) => write!(f, "highlight:{c}"),
+            RuleAction::Tag(tag) => write!(f, "tag:{tag}"),
+            RuleAction::Notify => write!(f, "notify"),
+        }
+    }
+}
+
+fn rules(rules: Vec<Spanned<PartialRule>>, problems: &mut Vec<Problem>) -> Vec<Rule> {
+    rules
+        .into_iter()
+        .filter_map(|rule| {
+            let span = rule.span();
+            Rule::try_from(rule.into_inner())
+                .inspect_err(|e| problems.push(Problem::new(Some(span), "", e)))
+                .ok()
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 struct PartialFeedSource {
     url: Option<FeedId>,
+    #[serde(default)]
     tags: Vec<String>,
     manual_update: Option<bool>,
     notify: Option<bool>,
     filter: Option<PartialFeedFilter>,
+    #[serde(default)]
+    rules: Vec<Spanned<PartialRule>>,
     max_items: Option<u32>,
+    timeout: Option<u64>,
+    update_interval: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -154,18 +474,123 @@ pub struct FeedSource {
     pub manual_update: bool,
     pub notify: bool,
     pub filter: Option<FeedFilter>,
+    #[serde(skip)]
+    pub rules: Vec<Rule>,
     pub max_items: u32,
+    pub timeout: Option<u64>,
+    pub update_interval: Option<u64>,
 }
-impl From<PartialFeedSource> for FeedSource {
-    fn from(value: PartialFeedSource) -> Self {
-        Self {
-            url: value.url.expect("url is required"),
-            tags: value.tags,
-            manual_update: value.manual_update.unwrap_or(false),
-            notify: value.notify.unwrap_or(false),
-            filter: value.filter.map(FeedFilter::from),
-            max_items: value.max_items.unwrap_or(5000),
+impl PartialFeedSource {
+    fn from_outline(outline: &Outline, url: &str, tags: Vec<String>) -> Result<Self, String> {
+        let filter = match outline.attr("rrss:filter") {
+            Some(pattern) => {
+                let filter = PartialFeedFilter {
+                    pattern: pattern.to_string(),
+                    invert: setting(outline, "rrss:filterInvert")?,
+                    case_insensitive: setting(outline, "rrss:filterCaseInsensitive")?,
+                };
+                let _ = RegexBuilder::new(&filter.pattern)
+                    .build()
+                    .map_err(|e| format!("invalid filter: {e}"))?;
+                Some(filter)
+            }
+            None => None,
+        };
+        let rules = match outline.attr("rrss:rules") {
+            Some(rules) => serde_json::from_str::<Vec<PartialRule>>(rules)
+                .map_err(|e| format!("invalid rrss:rules: {e}"))?,
+            None => vec![],
+        };
+        for rule in &rules {
+            let _ = Rule::try_from(rule.clone()).map_err(|e| format!("invalid rule: {e}"))?;
+        }
+        Ok(Self {
+            url: Some(FeedId(url.to_string())),
+            tags,
+            manual_update: setting(outline, "rrss:manualUpdate")?,
+            notify: setting(outline, "rrss:notify")?,
+            filter,
+            rules: rules.into_iter().map(|r| Spanned::new(0..0, r)).collect(),
+            max_items: setting(outline, "rrss:maxItems")?,
+            timeout: setting(outline, "rrss:timeout")?,
+            update_interval: setting(outline, "rrss:updateInterval")?,
+        })
+    }
+    fn build(
+        self,
+        url: FeedId,
+        span: Option<Range<usize>>,
+        problems: &mut Vec<Problem>,
+    ) -> FeedSource {
+        let filter = self.filter.and_then(|filter| {
+            FeedFilter::try_from(filter)
+                .inspect_err(|e| {
+                    let reason = format!("invalid filter for {}: {e}", url.0);
+                    problems.push(Problem::new(span.clone(), "filter", reason))
+                })
+                .ok()
+        });
+        FeedSource {
+            rules: rules(self.rules, problems),
+            url,
+            tags: self.tags,
+            manual_update: self.manual_update.unwrap_or(false),
+            notify: self.notify.unwrap_or(false),
+            filter,
+            max_items: self.max_items.unwrap_or(5000),
+            timeout: self.timeout,
+            update_interval: self.update_interval,
+        }
+    }
+}
+impl FeedSource {
+    pub fn new(url: FeedId, tags: Vec<String>) -> Self {
+        let source = PartialFeedSource {
+            tags,
+            ..Default::default()
+        };
+        source.build(url, None, &mut vec![])
+    }
+    pub fn to_outline(&self, name: &str, feed_type: &FeedType) -> Outline {
+        let mut outline = Outline::new(name).with("title", name);
+        if let Some(kind) = outline_type(feed_type) {
+            outline = outline.with("type", kind);
+        }
+        outline = outline.with("xmlUrl", &self.url.0);
+        if !self.tags.is_empty() {
+            let category = self
+                .tags
+                .iter()
+                .map(|t| format!("/{}", t.replace('%', "%25").replace(',', "%2C")))
+                .join(",");
+            outline = outline.with("category", &category);
+        }
+        outline = outline
+            .with("rrss:maxItems", &self.max_items.to_string())
+            .with("rrss:notify", &self.notify.to_string())
+            .with("rrss:manualUpdate", &self.manual_update.to_string());
+        if let Some(filter) = &self.filter {
+            outline = outline
+                .with("rrss:filter", &filter.pattern)
+                .with("rrss:filterInvert", &filter.invert.to_string())
+                .with(
+                    "rrss:filterCaseInsensitive",
+                    &filter.case_insensitive.to_string(),
+                );
+        }
+        if let Some(timeout) = self.timeout {
+            outline = outline.with("rrss:timeout", &timeout.to_string());
+        }
+        if let Some(interval) = self.update_interval {
+            outline = outline.with("rrss:updateInterval", &interval.to_string());
         }
+        if !self.rules.is_empty() {
+            let rules = self.rules.iter().map(PartialRule::from).collect_vec();
+            if let Ok(rules) = serde_json::to_string(&rules) {
+                outline = outline.with("rrss:rules", &rules);
+            }
+        }
+        outline
     }
 }
 impl PartialEq for FeedSource {
@@ -181,18 +606,59 @@ impl Hash for FeedSource {
 }
 
 #[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartialSavedSearch {
+    name: String,
+    query: String,
+    sort: Option<String>,
+}
+#[derive(Clone)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+    pub filter: Filter,
+    pub sorter: Sorter<models::Item>,
+}
+impl TryFrom<PartialSavedSearch> for SavedSearch {
+    type Error = String;
+    fn try_from(val: PartialSavedSearch) -> Result<Self, Self::Error> {
+        let filter = Filter::parse(&val.query).map_err(|e| e.to_string())?;
+        let sorter = match val.sort.as_deref().unwrap_or("posted_rev") {
+            "posted" => models::Item::BY_POSTED,
+            "posted_rev" => models::Item::BY_POSTED_REV,
+            "title" => models::Item::BY_TITLE,
+            "title_rev" => models::Item::BY_TITLE_REV,
+            "read_on" => models::Item::BY_READ_ON,
+            sort => return Err(format!("unknown sort '{sort}'")),
+        };
+        Ok(Self {
+            name: val.name,
+            query: val.query,
+            filter,
+            sorter,
+        })
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct PartialSources {
-    sources: Option<Vec<PartialFeedSource>>,
+    sources: Option<Vec<Spanned<PartialFeedSource>>>,
+    searches: Option<Vec<Spanned<PartialSavedSearch>>>,
 }
 pub struct Sources {
     sources: Vec<FeedSource>,
+    searches: Vec<SavedSearch>,
 }
 impl Sources {
-    pub fn to_feeds(self, mut feeds: Vec<Feed>) -> Vec<Feed> {
+    pub fn searches(&self) -> &[SavedSearch] {
+        &self.searches
+    }
+    pub fn to_feeds(self, mut feeds: Vec<CachedFeed>) -> Vec<Feed> {
         self.sources
             .into_iter()
             .map(
-                |source| match feeds.iter().position(|f| *f.id() == source.url) {
+                |source| match feeds.iter().position(|f| f.id == source.url) {
                     Some(idx) => {
                         let feed = feeds.swap_remove(idx);
                         let mut feed = Feed {
@@ -200,53 +666,522 @@ impl Sources {
                             data: feed.data,
                             state: feed.state,
                         };
-                        feed.refresh_items_state();
+                        feed.refresh_items_state(&[]);
                         feed
                     }
-                    _ => Feed {
-                        conf: source,
-                        data: None,
-                        state: FeedState::default(),
-                    },
+                    _ => Feed::new(source),
                 },
             )
             .collect()
     }
 }
-impl From<OPML> for Sources {
-    fn from(value: OPML) -> Self {
-        Sources {
-            sources: value
-                .body
-                .outlines
-                .into_iter()
-                .map(|item| FeedSource {
-                    url: FeedId(item.url.unwrap()),
-                    tags: vec![item.category.unwrap()],
-                    manual_update: false,
-                    notify: false,
-                    filter: None,
-                    max_items: 5000,
-                })
-                .collect(),
+impl Sources {
+    pub fn load() -> (Self, Vec<ConfigError>) {
+        let (file, val) = TomlFile::parse::<PartialSources>(SOURCES_FILE);
+        let mut problems = vec![];
+        let sources = Self::build(val, &mut problems);
+        (sources, file.resolve(problems))
+    }
+    fn build(val: PartialSources, problems: &mut Vec<Problem>) -> Self {
+        let mut searches: Vec<SavedSearch> = vec![];
+        for search in val.searches.unwrap_or_default() {
+            let span = Some(search.span());
+            let search = search.into_inner();
+            let name = search.name.clone();
+            match SavedSearch::try_from(search) {
+                Ok(_) if searches.iter().any(|s| s.name == name) => {
+                    let reason = format!("duplicate search {:?}", name);
+                    problems.push(Problem::new(span, "name", reason))
+                }
+                Ok(search) => searches.push(search),
+                Err(e) => problems.push(Problem::new(span, "", e)),
+            }
         }
+        let mut sources: Vec<FeedSource> = vec![];
+        for source in val.sources.unwrap_or_default() {
+            let span = Some(source.span());
+            let source = source.into_inner();
+            match source.url.clone() {
+                None => problems.push(Problem::new(span, "url", "missing url")),
+                Some(url) if sources.iter().any(|s| s.url == url) => {
+                    let reason = format!("duplicate source {:?}", url.0);
+                    problems.push(Problem::new(span, "url", reason))
+                }
+                Some(url) => sources.push(source.build(url, span, problems)),
+            }
+        }
+        Self { sources, searches }
     }
 }
-impl From<PartialSources> for Sources {
-    fn from(val: PartialSources) -> Self {
-        if let Some(sources) = val.sources {
-            let sources = sources.into_iter().map(FeedSource::from).collect_vec();
-            let prev_size = sources.len();
-            let uniques = sources.iter().cloned().unique().collect_vec();
-            if prev_size != uniques.len() {
-                for (src, freq) in sources.into_iter().counts() {
-                    if freq > 1 {
-                        println!("[warning] duplicate source: {:?}", src.url);
+
+// latest modification of config.toml or sources.toml, to watch them for changes
+pub fn config_modified() -> Option<SystemTime> {
+    let dir = ProjectDirs::from("", "", PROJECT_NAME).unwrap();
+    [CONFIG_FILE, SOURCES_FILE]
+        .into_iter()
+        .filter_map(|file| fs::metadata(dir.config_dir().join(file)).ok())
+        .filter_map(|meta| meta.modified().ok())
+        .max()
+}
+
+pub fn check_config() -> Vec<ConfigError> {
+    let (_, config) = Config::load();
+    let (_, sources) = Sources::load();
+    config.into_iter().chain(sources).collect()
+}
+
+pub struct OpmlImport {
+    pub sources: Vec<FeedSource>,
+    pub skipped: Vec<String>,
+}
+impl OpmlImport {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let opml = Opml::parse(&fs::read_to_string(path)?)?;
+        Ok(Self::from(opml))
+    }
+    fn walk(&mut self, outlines: Vec<Outline>, folders: &mut Vec<String>) {
+        for outline in outlines {
+            let name = outline
+                .attr("title")
+                .filter(|t| !t.trim().is_empty())
+                .or(outline.attr("text"))
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            let url = outline
+                .attr("xmlUrl")
+                .or(outline.attr("url"))
+                .map(str::trim)
+                .filter(|u| !u.is_empty());
+            match url {
+                Some(url) if !is_feed_url(url) => {
+                    self.skipped.push(format!("{name}: invalid url {url:?}"))
+                }
+                Some(url) => {
+                    let tags = folders
+                        .iter()
+                        .cloned()
+                        .chain(categories(outline.attr("category")))
+                        .unique()
+                        .collect_vec();
+                    if let Some(source) = self.sources.iter_mut().find(|s| s.url.0 == url) {
+                        source.tags = source.tags.drain(..).chain(tags).unique().collect();
+                        continue;
+                    }
+                    match PartialFeedSource::from_outline(&outline, url, tags) {
+                        Ok(source) => {
+                            let url = FeedId(url.to_string());
+                            self.sources.push(source.build(url, None, &mut vec![]))
+                        }
+                        Err(reason) => self.skipped.push(format!("{name}: {reason}")),
                     }
                 }
+                None if outline.outlines.is_empty() => {
+                    self.skipped.push(format!("{name}: missing feed url"))
+                }
+                None if name.is_empty() => self.walk(outline.outlines, folders),
+                None => {
+                    folders.push(name);
+                    self.walk(outline.outlines, folders);
+                    let _ = folders.pop();
+                }
+            }
+        }
+    }
+}
+impl From<Opml> for OpmlImport {
+    fn from(value: Opml) -> Self {
+        let mut import = Self {
+            sources: vec![],
+            skipped: vec![],
+        };
+        import.walk(value.outlines, &mut vec![]);
+        import
+    }
+}
+
+pub struct SourcesFile {
+    path: PathBuf,
+    doc: DocumentMut,
+}
+impl SourcesFile {
+    pub fn open() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = ProjectDirs::from("", "", PROJECT_NAME)
+            .unwrap()
+            .config_dir()
+            .join(SOURCES_FILE);
+        let doc = match fs::read_to_string(&path) {
+            Ok(content) => content.parse::<DocumentMut>()?,
+            Err(e) if e.kind() == ErrorKind::NotFound => DocumentMut::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path, doc })
+    }
+    pub fn contains(&self, url: &FeedId) -> bool {
+        self.doc
+            .get("sources")
+            .and_then(Item::as_array_of_tables)
+            .is_some_and(|sources| sources.iter().any(|s| is_source(s, url)))
+    }
+    pub fn add(&mut self, source: &FeedSource) -> Result<(), Box<dyn std::error::Error>> {
+        let sources = self
+            .doc
+            .entry("sources")
+            .or_insert(Item::ArrayOfTables(ArrayOfTables::new()))
+            .as_array_of_tables_mut()
+            .ok_or("`sources` must be an array of tables")?;
+        if !is_feed_url(&source.url.0) {
+            return Err(format!("invalid url {:?}", source.url.0).into());
+        }
+        let default = FeedSource::new(source.url.clone(), vec![]);
+        let mut table = Table::new();
+        table["url"] = value(source.url.0.as_str());
+        table["tags"] = value(source.tags.iter().map(String::as_str).collect::<Array>());
+        if source.manual_update != default.manual_update {
+            table["manual_update"] = value(source.manual_update);
+        }
+        if source.notify != default.notify {
+            table["notify"] = value(source.notify);
+        }
+        if let Some(filter) = &source.filter {
+            let mut inline = InlineTable::new();
+            let _ = inline.insert("pattern", filter.pattern.as_str().into());
+            let _ = inline.insert("invert", filter.invert.into());
+            let _ = inline.insert("case_insensitive", filter.case_insensitive.into());
+            table["filter"] = value(inline);
+        }
+        if source.max_items != default.max_items {
+            table["max_items"] = value(source.max_items as i64);
+        }
+        if let Some(timeout) = source.timeout {
+            table["timeout"] = value(timeout as i64);
+        }
+        if let Some(interval) = source.update_interval {
+            table["update_interval"] = value(interval as i64);
+        }
+        if !source.rules.is_empty() {
+            let rules = source
+                .rules
+                .iter()
+                .map(|rule| PartialRule::from(rule).serialize(ValueSerializer::new()))
+                .collect::<Result<Array, _>>()?;
+            table["rules"] = value(rules);
+        }
+        sources.push(table);
+        Ok(())
+    }
+    pub fn remove(&mut self, url: &FeedId) -> Result<(), Box<dyn std::error::Error>> {
+        let sources = self
+            .doc
+            .get_mut("sources")
+            .and_then(Item::as_array_of_tables_mut)
+            .ok_or_else(|| not_subscribed(url))?;
+        let idx = sources
+            .iter()
+            .position(|s| is_source(s, url))
+            .ok_or_else(|| not_subscribed(url))?;
+        sources.remove(idx);
+        Ok(())
+    }
+    // returns whether the source didn't have the tag yet
+    pub fn add_tag(&mut self, url: &FeedId, tag: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let tags = self
+            .source_mut(url)?
+            .entry("tags")
+            .or_insert(value(Array::new()))
+            .as_array_mut()
+            .ok_or("`tags` must be an array")?;
+        if tags.iter().any(|t| t.as_str() == Some(tag)) {
+            return Ok(false);
+        }
+        tags.push(tag);
+        Ok(true)
+    }
+    pub fn set_tags(
+        &mut self,
+        url: &FeedId,
+        tags: &[String],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.source_mut(url)?["tags"] = value(tags.iter().collect::<Array>());
+        Ok(())
+    }
+    pub fn set_max_items(
+        &mut self,
+        url: &FeedId,
+        max_items: u32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.source_mut(url)?["max_items"] = value(max_items as i64);
+        Ok(())
+    }
+    fn source_mut(&mut self, url: &FeedId) -> Result<&mut Table, Box<dyn std::error::Error>> {
+        self.doc
+            .get_mut("sources")
+            .and_then(Item::as_array_of_tables_mut)
+            .and_then(|sources| sources.iter_mut().find(|s| is_source(s, url)))
+            .ok_or_else(|| not_subscribed(url))
+    }
+    pub fn save(&self) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("toml.tmp");
+        fs::write(&tmp, self.doc.to_string())?;
+        fs::File::open(&tmp)?.sync_all()?;
+        fs::rename(tmp, &self.path)
+    }
+}
+
+fn is_source(table: &Table, url: &FeedId) -> bool {
+    table.get("url").and_then(Item::as_str) == Some(url.0.as_str())
+}
+
+fn not_subscribed(url: &FeedId) -> Box<dyn std::error::Error> {
+    format!("not subscribed to {}", url.0).into()
+}
+
+fn is_feed_url(url: &str) -> bool {
+    url.parse::<Uri>()
+        .is_ok_and(|uri| matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some())
+}
+
+// feeds that were never fetched have no known type
+fn outline_type(feed_type: &FeedType) -> Option<&'static str> {
+    match feed_type.0.as_ref()? {
+        feed_rs::model::FeedType::Atom => Some("atom"),
+        feed_rs::model::FeedType::JSON => Some("json"),
+        feed_rs::model::FeedType::RSS0
+        | feed_rs::model::FeedType::RSS1
+        | feed_rs::model::FeedType::RSS2 => Some("rss"),
+    }
+}
+
+// the exporter writes each tag as a `/tag` path, so only commas separate them;
+// commas and percent signs inside a tag are percent-escaped
+fn categories(category: Option<&str>) -> Vec<String> {
+    category
+        .unwrap_or_default()
+        .split(',')
+        .map(|c| c.trim().trim_start_matches('/').trim())
+        .filter(|c| !c.is_empty())
+        .map(unescape_category)
+        .collect()
+}
+
+fn unescape_category(category: &str) -> String {
+    let mut parts = category.split('%');
+    let mut tag = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        match part.get(..2) {
+            Some(code) if code.eq_ignore_ascii_case("2c") => tag.push(','),
+            Some("25") => tag.push('%'),
+            _ => {
+                tag.push('%');
+                tag.push_str(part);
+                continue;
             }
-            return Sources { sources: uniques };
         }
-        Sources { sources: vec![] }
+        tag.push_str(&part[2..]);
+    }
+    tag
+}
+
+fn setting<T: FromStr>(outline: &Outline, key: &str) -> Result<Option<T>, String> {
+    outline
+        .attr(key)
+        .map(|v| v.trim().parse().map_err(|_| format!("invalid {key} {v:?}")))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(body: &str) -> OpmlImport {
+        let xml = format!(r#"<?xml version="1.0"?><opml version="2.0"><body>{body}</body></opml>"#);
+        OpmlImport::from(Opml::parse(&xml).unwrap())
+    }
+    fn tags(import: &OpmlImport, url: &str) -> Vec<String> {
+        let source = import.sources.iter().find(|s| s.url.0 == url).unwrap();
+        source.tags.clone()
+    }
+
+    #[test]
+    fn folders_become_tags() {
+        let import = import(
+            r#"<outline text="Tech">
+                <outline text="Rust" title=" ">
+                    <outline text="blog" xmlUrl="https://blog.rust-lang.org/feed.xml"/>
+                </outline>
+                <outline title="ignored" text="">
+                    <outline text="lwn" xmlUrl=" https://lwn.net/headlines/rss " category="/news,/lang/sys"/>
+                </outline>
+            </outline>
+            <outline>
+                <outline text="top" url="https://example.com/feed"/>
+            </outline>"#,
+        );
+        assert!(import.skipped.is_empty(), "{:?}", import.skipped);
+        assert_eq!(
+            tags(&import, "https://blog.rust-lang.org/feed.xml"),
+            vec!["Tech", "Rust"]
+        );
+        assert_eq!(
+            tags(&import, "https://lwn.net/headlines/rss"),
+            vec!["Tech", "ignored", "news", "lang/sys"]
+        );
+        assert!(tags(&import, "https://example.com/feed").is_empty());
+    }
+
+    #[test]
+    fn duplicate_feeds_merge_their_tags() {
+        let import = import(
+            r#"<outline text="a"><outline text="x" xmlUrl="https://example.com/feed"/></outline>
+            <outline text="b"><outline text="x" xmlUrl="https://example.com/feed" category="a,c"/></outline>"#,
+        );
+        assert_eq!(import.sources.len(), 1);
+        assert_eq!(
+            tags(&import, "https://example.com/feed"),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn bad_outlines_are_skipped() {
+        let import = import(
+            r#"<outline text="empty folder"/>
+            <outline text="ftp" xmlUrl="ftp://example.com/feed"/>
+            <outline text="relative" xmlUrl="/feed.xml"/>
+            <outline text="limit" xmlUrl="https://example.com/a" rrss:maxItems="many"/>
+            <outline text="regex" xmlUrl="https://example.com/b" rrss:filter="("/>
+            <outline text="ok" xmlUrl="https://example.com/c"/>"#,
+        );
+        assert_eq!(import.sources.len(), 1);
+        assert_eq!(import.skipped.len(), 5);
+        assert_eq!(
+            import.skipped[..4],
+            [
+                "empty folder: missing feed url",
+                "ftp: invalid url \"ftp://example.com/feed\"",
+                "relative: invalid url \"/feed.xml\"",
+                "limit: invalid rrss:maxItems \"many\"",
+            ]
+        );
+        assert!(import.skipped[4].starts_with("regex: invalid filter"));
+    }
+
+    #[test]
+    fn exported_settings_are_imported_back() {
+        let mut source = FeedSource::new(
+            FeedId("https://example.com/feed".into()),
+            vec![
+                "news".into(),
+                "lang/sys".into(),
+                "a, b".into(),
+                "100%".into(),
+            ],
+        );
+        source.notify = true;
+        source.max_items = 10;
+        source.update_interval = Some(60);
+        source.rules = rules(
+            vec![Spanned::new(
+                0..0,
+                PartialRule {
+                    name: Some("ads".into()),
+                    field: Some("content".into()),
+                    pattern: "sponsored".into(),
+                    case_insensitive: Some(true),
+                    invert: None,
+                    actions: vec!["read".into(), "tag:ads".into()],
+                },
+            )],
+            &mut vec![],
+        );
+        let opml = Opml {
+            title: None,
+            outlines: vec![source.to_outline("Example", &FeedType(None))],
+        };
+        assert_eq!(opml.outlines[0].attr("type"), None);
+        let import = OpmlImport::from(Opml::parse(&opml.to_xml().unwrap()).unwrap());
+        assert!(import.skipped.is_empty(), "{:?}", import.skipped);
+        let [imported] = import.sources.as_slice() else {
+            panic!("expected one source");
+        };
+        assert_eq!(imported.tags, source.tags);
+        assert!(imported.notify);
+        assert_eq!(imported.max_items, 10);
+        assert_eq!(imported.update_interval, Some(60));
+        assert_eq!(imported.rules.len(), 1);
+        let rule = &imported.rules[0];
+        assert_eq!(rule.name, "ads");
+        assert!(rule.case_insensitive && rule.pattern.is_match("SPONSORED"));
+        assert_eq!(
+            rule.actions.iter().map(|a| a.to_string()).collect_vec(),
+            vec!["read", "tag:ads"]
+        );
+    }
+
+    #[test]
+    fn added_rules_are_loaded_back() {
+        let mut sources = SourcesFile {
+            path: PathBuf::new(),
+            doc: DocumentMut::new(),
+        };
+        let mut source = FeedSource::new(FeedId("https://example.com/feed".into()), vec![]);
+        source.rules = rules(
+            vec![Spanned::new(
+                0..0,
+                PartialRule {
+                    name: None,
+                    field: Some("title".into()),
+                    pattern: "\\[ad\\]".into(),
+                    case_insensitive: None,
+                    invert: Some(true),
+                    actions: vec!["highlight:red".into()],
+                },
+            )],
+            &mut vec![],
+        );
+        sources.add(&source).unwrap();
+
+        let val = toml_edit::de::from_str(&sources.doc.to_string()).unwrap();
+        let mut problems = vec![];
+        let loaded = Sources::build(val, &mut problems);
+        assert!(problems.is_empty());
+        let rules = &loaded.sources[0].rules;
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].name, source.rules[0].name);
+        assert_eq!(rules[0].field, RuleField::Title);
+        assert_eq!(rules[0].pattern.as_str(), "\\[ad\\]");
+        assert!(rules[0].invert && !rules[0].case_insensitive);
+        assert!(matches!(
+            rules[0].actions[..],
+            [RuleAction::Highlight(Some(Color::Red))]
+        ));
+    }
+
+    #[test]
+    fn add_tag_only_adds_missing_tags() {
+        let mut sources = SourcesFile {
+            path: PathBuf::new(),
+            doc: DocumentMut::new(),
+        };
+        let url = FeedId("https://example.com/feed".to_string());
+        sources
+            .add(&FeedSource::new(url.clone(), vec!["news".to_string()]))
+            .unwrap();
+        assert!(!sources.add_tag(&url, "news").unwrap());
+        assert!(sources.add_tag(&url, "tech").unwrap());
+        assert!(!sources.add_tag(&url, "tech").unwrap());
+        let tags = sources.source_mut(&url).unwrap()["tags"]
+            .as_array()
+            .unwrap();
+        assert_eq!(
+            tags.iter().filter_map(|t| t.as_str()).collect_vec(),
+            vec!["news", "tech"]
+        );
+        assert!(sources
+            .add_tag(&FeedId("https://other.com".to_string()), "x")
+            .is_err());
     }
 }
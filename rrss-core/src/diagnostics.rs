@@ -0,0 +1,331 @@
+use std::fmt::Display;
+use std::fs;
+use std::io::ErrorKind;
+use std::ops::Range;
+
+use directories::ProjectDirs;
+use itertools::Itertools;
+use serde::de::DeserializeOwned;
+use toml_edit::{ImDocument, Item, Table};
+
+use crate::error::ConfigError;
+use crate::globals::PROJECT_NAME;
+
+// a problem found while building the config, located by the span of the
+// entry it was found in and a key relative to that entry
+pub struct Problem {
+    pub span: Option<Range<usize>>,
+    pub key: String,
+    pub reason: String,
+}
+impl Problem {
+    pub fn new(span: Option<Range<usize>>, key: &str, reason: impl Display) -> Self {
+        Self {
+            span,
+            key: key.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+pub struct TomlFile {
+    name: &'static str,
+    text: String,
+    doc: Option<ImDocument<String>>,
+    errors: Vec<ConfigError>,
+}
+impl TomlFile {
+    // deserializes the file, dropping every entry serde rejects so that a
+    // single typo does not discard the rest of the file
+    pub fn parse<P: DeserializeOwned + Default>(name: &'static str) -> (Self, P) {
+        let path = ProjectDirs::from("", "", PROJECT_NAME)
+            .unwrap()
+            .config_dir()
+            .join(name);
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse_text(name, text),
+            Err(e) if e.kind() == ErrorKind::NotFound => (Self::new(name, ""), P::default()),
+            Err(e) => {
+                let mut file = Self::new(name, "");
+                file.push(Problem::new(None, "", e));
+                (file, P::default())
+            }
+        }
+    }
+    fn new(name: &'static str, text: &str) -> Self {
+        Self {
+            name,
+            text: text.to_string(),
+            doc: None,
+            errors: vec![],
+        }
+    }
+    fn parse_text<P: DeserializeOwned + Default>(name: &'static str, text: String) -> (Self, P) {
+        let mut file = Self::new(name, &text);
+        match file.text.parse::<ImDocument<String>>() {
+            Ok(doc) => file.doc = Some(doc),
+            Err(e) => {
+                file.push(Problem::new(e.span(), "", e.message()));
+                return (file, P::default());
+            }
+        }
+        let mut text = file.text.clone();
+        loop {
+            let doc = match text.parse::<ImDocument<String>>() {
+                Ok(doc) => doc,
+                Err(e) => {
+                    file.push(Problem::new(e.span(), "", e.message()));
+                    return (file, P::default());
+                }
+            };
+            let e = match toml_edit::de::from_document::<P>(doc) {
+                Ok(val) => return (file, val),
+                Err(e) => e,
+            };
+            let entry = e.span().and_then(|span| file.entry_at(span.start));
+            file.push(Problem::new(e.span(), "", e.message()));
+            match entry {
+                Some((_, range)) if !text[range.clone()].trim().is_empty() => {
+                    blank(&mut text, range)
+                }
+                _ => return (file, P::default()),
+            }
+        }
+    }
+    fn push(&mut self, problem: Problem) {
+        let (key, line) = self.locate(&problem);
+        self.errors.push(ConfigError {
+            file: self.name.to_string(),
+            line,
+            key,
+            reason: problem
+                .reason
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .join(" "),
+        });
+    }
+    pub fn resolve(mut self, problems: Vec<Problem>) -> Vec<ConfigError> {
+        problems.into_iter().for_each(|p| self.push(p));
+        self.errors.sort_by_key(|e| e.line);
+        self.errors
+    }
+    pub fn line(&self, key: &str) -> Option<usize> {
+        let span = span_of(self.doc.as_ref()?.as_item(), key)?;
+        Some(line_of(&self.text, span.start))
+    }
+    fn locate(&self, problem: &Problem) -> (String, Option<usize>) {
+        let line = |span: Option<Range<usize>>| span.map(|s| line_of(&self.text, s.start));
+        let Some(doc) = &self.doc else {
+            return (problem.key.clone(), line(problem.span.clone()));
+        };
+        let base = problem
+            .span
+            .as_ref()
+            .and_then(|span| self.entry_at(span.start))
+            .map(|(path, _)| path)
+            .unwrap_or_default();
+        let key = match (base.is_empty(), problem.key.is_empty()) {
+            (true, _) => problem.key.clone(),
+            (false, true) => base,
+            (false, false) => format!("{}.{}", base, problem.key),
+        };
+        let span = span_of(doc.as_item(), &key).or(problem.span.clone());
+        (key, line(span))
+    }
+    // the innermost table entry containing the offset, with the range that
+    // covers both its key and its value
+    fn entry_at(&self, offset: usize) -> Option<(String, Range<usize>)> {
+        entry_at(self.doc.as_ref()?.as_table(), offset, "")
+    }
+}
+
+fn entry_at(table: &Table, offset: usize, prefix: &str) -> Option<(String, Range<usize>)> {
+    for (name, item) in table.iter() {
+        let path = match prefix {
+            "" => name.to_string(),
+            prefix => format!("{prefix}.{name}"),
+        };
+        let key = table.key(name).and_then(|k| k.span());
+        let range = match (key, item.span()) {
+            (Some(k), Some(v)) => Some(k.start.min(v.start)..k.end.max(v.end)),
+            (k, v) => k.or(v),
+        };
+        match item {
+            Item::Table(t) => {
+                if let Some(found) = entry_at(t, offset, &path) {
+                    return Some(found);
+                }
+            }
+            Item::ArrayOfTables(tables) => {
+                for (idx, t) in tables.iter().enumerate() {
+                    let path = format!("{path}[{idx}]");
+                    if let Some(found) = entry_at(t, offset, &path) {
+                        return Some(found);
+                    }
+                    if let Some(span) = t.span() {
+                        if span.contains(&offset) {
+                            return Some((path, span));
+                        }
+                    }
+                }
+                // the array spans from its first to its last entry, which
+                // may enclose other tables
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(range) = range {
+            if range.contains(&offset) {
+                return Some((path, range));
+            }
+        }
+    }
+    None
+}
+
+// span of the deepest item found along a path like `sources[2].filter`
+fn span_of(root: &Item, path: &str) -> Option<Range<usize>> {
+    let mut item = root;
+    let mut span = None;
+    for seg in path.split('.').filter(|s| !s.is_empty()) {
+        let (name, idx) = match seg.split_once('[') {
+            Some((name, idx)) => (name, idx.trim_end_matches(']').parse::<usize>().ok()),
+            None => (seg, None),
+        };
+        item = match item.get(name) {
+            Some(item) => item,
+            None => break,
+        };
+        span = item.span().or(span);
+        if let Some(idx) = idx {
+            item = match item.get(idx) {
+                Some(item) => item,
+                None => break,
+            };
+            span = item.span().or(span);
+        }
+    }
+    span
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    text.get(..offset).unwrap_or(text).matches('\n').count() + 1
+}
+
+// replaces a range with spaces, keeping offsets and line numbers intact
+fn blank(text: &mut String, range: Range<usize>) {
+    let blanked = text[range.clone()]
+        .chars()
+        .map(|c| match c {
+            '\n' => "\n".to_string(),
+            c => " ".repeat(c.len_utf8()),
+        })
+        .collect::<String>();
+    text.replace_range(range, &blanked);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Default, Debug)]
+    #[serde(deny_unknown_fields)]
+    struct Config {
+        name: Option<String>,
+        sources: Option<Vec<Source>>,
+    }
+    #[derive(Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    struct Source {
+        url: String,
+        max: Option<u32>,
+    }
+
+    fn parse(text: &str) -> (TomlFile, Config) {
+        TomlFile::parse_text("test.toml", text.to_string())
+    }
+    fn errors(file: TomlFile) -> Vec<(Option<usize>, String)> {
+        file.resolve(vec![])
+            .into_iter()
+            .map(|e| (e.line, e.key))
+            .collect()
+    }
+    fn urls(config: &Config) -> Vec<&str> {
+        config
+            .sources
+            .iter()
+            .flatten()
+            .map(|s| s.url.as_str())
+            .collect()
+    }
+
+    const SOURCES: &str = r#"name = "x"
+
+[[sources]]
+url = "a"
+
+[[sources]]
+url = "b"
+max = "many"
+
+[[sources]]
+max = 1
+
+[[sources]]
+url = "d"
+typo = true
+"#;
+
+    #[test]
+    fn bad_entries_are_dropped_and_located() {
+        let (file, config) = parse(SOURCES);
+        assert_eq!(config.name.as_deref(), Some("x"));
+        assert_eq!(urls(&config), vec!["a", "b", "d"]);
+        assert_eq!(config.sources.unwrap()[1].max, None);
+        assert_eq!(
+            errors(file),
+            vec![
+                (Some(8), "sources[1].max".into()),
+                (Some(10), "sources[2]".into()),
+                (Some(15), "sources[3].typo".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn syntax_errors_keep_nothing() {
+        let (file, config) = parse("name = \"x\"\n[[sources]\nurl = \"a\"\n");
+        assert!(config.name.is_none() && config.sources.is_none());
+        assert_eq!(errors(file), vec![(Some(2), "".into())]);
+    }
+
+    #[test]
+    fn problems_resolve_relative_to_their_entry() {
+        let (file, _) = parse(SOURCES);
+        assert_eq!(file.line("sources[3].url"), Some(14));
+        assert_eq!(file.line("name"), Some(1));
+        assert_eq!(file.line("missing"), None);
+        let entry = SOURCES.find("[[sources]]\nurl = \"d\"").unwrap();
+        let problems = vec![
+            Problem::new(Some(entry..entry + 1), "url", "bad url"),
+            Problem::new(None, "name", "bad\n   name\n"),
+        ];
+        let errors = file
+            .resolve(problems)
+            .into_iter()
+            .map(|e| (e.line, e.key, e.reason))
+            .filter(|(_, _, reason)| reason.starts_with("bad"))
+            .collect_vec();
+        assert_eq!(
+            errors,
+            vec![
+                (Some(1), "name".into(), "bad name".into()),
+                (Some(14), "sources[3].url".into(), "bad url".into()),
+            ]
+        );
+    }
+}
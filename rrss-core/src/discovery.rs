@@ -0,0 +1,275 @@
+use std::cmp::Ordering;
+use std::time::Duration;
+
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use ratatui::layout::Constraint;
+use ratatui_helpers::stateful_table::Tabular;
+use regex::Regex;
+use url::Url;
+
+use crate::error::FetchError;
+use crate::models::{FeedId, FeedType};
+
+const FEED_MIME_TYPES: [&str; 4] = [
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+    "application/json",
+];
+const COMMON_PATHS: [&str; 8] = [
+    "/feed",
+    "/rss",
+    "/feed.xml",
+    "/rss.xml",
+    "/atom.xml",
+    "/index.xml",
+    "/feed.json",
+    "/feeds/posts/default",
+];
+
+lazy_static! {
+    static ref LINK: Regex = Regex::new(r"(?is)<link\b([^>]*)>").unwrap();
+    static ref ATTR: Regex =
+        Regex::new(r#"(?is)([a-z-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap();
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedCandidate {
+    pub url: FeedId,
+    pub title: String,
+    pub feed_type: FeedType,
+}
+impl Tabular for FeedCandidate {
+    type Value = FeedId;
+    fn cmp_by_col(&self, other: &Self, col: usize) -> Ordering {
+        match col {
+            0 => self.title.cmp(&other.title),
+            1 => self.feed_type.to_string().cmp(&other.feed_type.to_string()),
+            2 => self.url.0.cmp(&other.url.0),
+            _ => panic!(),
+        }
+    }
+    fn value(&self) -> Self::Value {
+        self.url.clone()
+    }
+    fn content(&self) -> Vec<String> {
+        vec![
+            format!("{}", self.title),
+            format!("{}", self.feed_type),
+            format!("{}", self.url.0),
+        ]
+    }
+    fn column_names() -> Option<Vec<String>> {
+        Some(vec![format!("Title"), format!("Type"), format!("Url")])
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![Constraint::Length, Constraint::Length, Constraint::Fill]
+    }
+}
+
+pub fn discover(url: &str, timeout: Duration) -> Result<Vec<FeedCandidate>, FetchError> {
+    let url = match url.contains("://") {
+        true => url.trim().to_string(),
+        false => format!("https://{}", url.trim()),
+    };
+    let base =
+        Url::parse(&url).map_err(|e| FetchError::Request(FeedId(url.clone()), e.to_string()))?;
+    let body = fetch(base.as_str(), timeout)?;
+    if let Some(candidate) = candidate(base.as_str(), &body, None) {
+        return Ok(vec![candidate]);
+    }
+
+    let links = alternate_links(&String::from_utf8_lossy(&body))
+        .into_iter()
+        .filter_map(|(href, title)| Some((base.join(&href).ok()?, title)))
+        .unique_by(|(url, _)| url.clone())
+        .collect_vec();
+    let mut candidates = probe(links, timeout);
+    if candidates.is_empty() {
+        let paths = COMMON_PATHS
+            .iter()
+            .filter_map(|path| Some((base.join(path).ok()?, None)))
+            .collect_vec();
+        candidates = probe(paths, timeout);
+    }
+    Ok(candidates)
+}
+
+// every url is fetched at once, so a site missing all of them costs one timeout
+// instead of one per url; candidates keep the order the urls were given in
+fn probe(urls: Vec<(Url, Option<String>)>, timeout: Duration) -> Vec<FeedCandidate> {
+    std::thread::scope(|s| {
+        let handles = urls
+            .into_iter()
+            .map(|(url, title)| {
+                s.spawn(move || {
+                    let body = fetch(url.as_str(), timeout).ok()?;
+                    candidate(url.as_str(), &body, title)
+                })
+            })
+            .collect_vec();
+        handles
+            .into_iter()
+            .filter_map(|h| h.join().ok().flatten())
+            .collect()
+    })
+}
+
+fn candidate(url: &str, body: &[u8], title: Option<String>) -> Option<FeedCandidate> {
+    let feed = feed_rs::parser::parse(body).ok()?;
+    let title = feed
+        .title
+        .map(|t| t.content)
+        .filter(|t| !t.trim().is_empty())
+        .or(title)
+        .unwrap_or_default();
+    Some(FeedCandidate {
+        url: FeedId(url.to_string()),
+        title,
+        feed_type: FeedType(Some(feed.feed_type)),
+    })
+}
+
+fn alternate_links(html: &str) -> Vec<(String, Option<String>)> {
+    LINK.captures_iter(html)
+        .filter_map(|link| {
+            let attrs = ATTR
+                .captures_iter(&link[1])
+                .filter_map(|a| {
+                    let value = a.get(2).or(a.get(3)).or(a.get(4))?;
+                    Some((a[1].to_lowercase(), value.as_str().replace("&amp;", "&")))
+                })
+                .collect_vec();
+            let get = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+            let is_alternate = get("rel").is_some_and(|rel| {
+                rel.split_whitespace()
+                    .any(|r| r.eq_ignore_ascii_case("alternate"))
+            });
+            let is_feed = get("type")
+                .is_some_and(|t| FEED_MIME_TYPES.contains(&t.trim().to_lowercase().as_str()));
+            match is_alternate && is_feed {
+                true => Some((get("href")?, get("title"))),
+                false => None,
+            }
+        })
+        .collect()
+}
+
+fn fetch(url: &str, timeout: Duration) -> Result<Vec<u8>, FetchError> {
+    ureq::get(url)
+        .config()
+        .timeout_global(Some(timeout))
+        .build()
+        .call()
+        .and_then(|res| res.into_body().read_to_vec())
+        .map_err(|e| FetchError::from_request(FeedId(url.to_string()), timeout, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::time::Instant;
+
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0"?><rss version="2.0"><channel><title>Rss</title><link>http://x</link><description>d</description></channel></rss>"#;
+    const ATOM: &str = r#"<?xml version="1.0"?><feed xmlns="http://www.w3.org/2005/Atom"><title>Atom</title><id>x</id><updated>2024-01-01T00:00:00Z</updated></feed>"#;
+
+    // serves `routes` as (path, delay, body) and 404 for anything else
+    fn serve(routes: Vec<(&'static str, u64, String)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _ = std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let routes = routes.clone();
+                let _ = std::thread::spawn(move || {
+                    let mut line = String::new();
+                    let _ = BufReader::new(&stream).read_line(&mut line);
+                    let path = line.split_whitespace().nth(1).unwrap_or("").to_string();
+                    let (status, body) = match routes.iter().find(|(p, _, _)| *p == path) {
+                        Some((_, delay, body)) => {
+                            std::thread::sleep(Duration::from_millis(*delay));
+                            ("200 OK", body.clone())
+                        }
+                        None => ("404 Not Found", String::new()),
+                    };
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                });
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    #[test]
+    fn alternate_links_keep_feed_types_only() {
+        let html = r#"<head>
+            <link rel="stylesheet" type="text/css" href="/style.css">
+            <LINK REL="Alternate" TYPE="application/atom+xml" HREF="/atom?a=1&amp;b=2" title="Atom feed">
+            <link type='application/rss+xml' rel='home alternate' href=/rss.xml>
+            <link rel="alternate" type="text/html" hreflang="fr" href="/fr">
+            <link rel="alternate" type="application/feed+json" title="Json">
+        </head>"#;
+        assert_eq!(
+            alternate_links(html),
+            vec![
+                ("/atom?a=1&b=2".to_string(), Some("Atom feed".to_string())),
+                ("/rss.xml".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn candidates_follow_page_order() {
+        let html = r#"<link rel="alternate" type="application/rss+xml" href="/slow.xml" title="Slow">
+            <link rel="alternate" type="application/atom+xml" href="/atom.xml">
+            <link rel="alternate" type="application/rss+xml" href="/slow.xml">
+            <link rel="alternate" type="application/rss+xml" href="/missing.xml">"#;
+        let base = serve(vec![
+            ("/", 0, html.to_string()),
+            (
+                "/slow.xml",
+                300,
+                RSS.replace("<title>Rss</title>", "<title></title>"),
+            ),
+            ("/atom.xml", 0, ATOM.to_string()),
+        ]);
+        let found = discover(&base, Duration::from_secs(5)).unwrap();
+        let found = found
+            .iter()
+            .map(|c| (c.url.0.trim_start_matches(&base), c.title.as_str()))
+            .collect_vec();
+        assert_eq!(found, vec![("/slow.xml", "Slow"), ("/atom.xml", "Atom")]);
+    }
+
+    #[test]
+    fn common_paths_are_probed_together() {
+        let base = serve(vec![
+            ("/", 0, "<html></html>".to_string()),
+            ("/feed", 0, "not a feed".to_string()),
+            ("/rss.xml", 400, RSS.to_string()),
+            ("/atom.xml", 400, ATOM.to_string()),
+        ]);
+        let start = Instant::now();
+        let found = discover(&base, Duration::from_secs(5)).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(800));
+        let found = found
+            .iter()
+            .map(|c| c.url.0.trim_start_matches(&base))
+            .collect_vec();
+        assert_eq!(found, vec!["/rss.xml", "/atom.xml"]);
+    }
+
+    #[test]
+    fn a_feed_url_is_its_own_candidate() {
+        let base = serve(vec![("/", 0, ATOM.to_string())]);
+        let found = discover(&base, Duration::from_secs(5)).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].title, "Atom");
+    }
+}
@@ -0,0 +1,204 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use feed_rs::parser::ParseFeedError;
+use ratatui::layout::Constraint;
+use ratatui_helpers::stateful_table::Tabular;
+
+use crate::models::FeedId;
+
+#[derive(Debug, Clone)]
+pub enum FetchError {
+    Connect(FeedId, String),
+    Status(FeedId, u16),
+    Timeout(FeedId, Duration),
+    Parse(FeedId, String),
+    Encoding(FeedId, String),
+    Request(FeedId, String),
+}
+impl FetchError {
+    pub fn from_request(id: FeedId, timeout: Duration, e: ureq::Error) -> Self {
+        match e {
+            ureq::Error::StatusCode(status) => Self::Status(id, status),
+            ureq::Error::Timeout(_) => Self::Timeout(id, timeout),
+            ureq::Error::Io(e) if e.kind() == ErrorKind::TimedOut => Self::Timeout(id, timeout),
+            ureq::Error::HostNotFound | ureq::Error::ConnectionFailed => {
+                Self::Connect(id, e.to_string())
+            }
+            ureq::Error::Io(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::ConnectionRefused
+                        | ErrorKind::ConnectionReset
+                        | ErrorKind::ConnectionAborted
+                        | ErrorKind::NotConnected
+                        | ErrorKind::AddrNotAvailable
+                ) =>
+            {
+                Self::Connect(id, e.to_string())
+            }
+            e => Self::Request(id, e.to_string()),
+        }
+    }
+    pub fn from_parse(id: FeedId, e: ParseFeedError) -> Self {
+        match e {
+            ParseFeedError::IoError(e) if e.kind() == ErrorKind::InvalidData => {
+                Self::Encoding(id, e.to_string())
+            }
+            // feed_rs keeps its xml error type private, so encoding errors
+            // can only be told apart by their message
+            ParseFeedError::XmlReader(e) if e.to_string().starts_with("Encoding error") => {
+                Self::Encoding(id, e.to_string())
+            }
+            e => Self::Parse(id, e.to_string()),
+        }
+    }
+    pub fn id(&self) -> &FeedId {
+        match self {
+            Self::Connect(id, _)
+            | Self::Status(id, _)
+            | Self::Timeout(id, _)
+            | Self::Parse(id, _)
+            | Self::Encoding(id, _)
+            | Self::Request(id, _) => id,
+        }
+    }
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Connect(..) => "connect",
+            Self::Status(..) => "status",
+            Self::Timeout(..) => "timeout",
+            Self::Parse(..) => "parse",
+            Self::Encoding(..) => "encoding",
+            Self::Request(..) => "request",
+        }
+    }
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Status(_, status) => Some(*status),
+            _ => None,
+        }
+    }
+    pub fn reason(&self) -> String {
+        match self {
+            Self::Status(_, status) => format!("HTTP {}", status),
+            Self::Timeout(_, after) => format!("no response after {}s", after.as_secs()),
+            Self::Connect(_, reason)
+            | Self::Parse(_, reason)
+            | Self::Encoding(_, reason)
+            | Self::Request(_, reason) => reason.clone(),
+        }
+    }
+}
+impl Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}: {}", self.kind(), self.id().0, self.reason())
+    }
+}
+impl std::error::Error for FetchError {}
+
+impl Tabular for FetchError {
+    type Value = FeedId;
+    fn cmp_by_col(&self, other: &Self, col: usize) -> Ordering {
+        match col {
+            0 => self.id().0.cmp(&other.id().0),
+            1 => self.kind().cmp(other.kind()),
+            2 => self.reason().cmp(&other.reason()),
+            _ => panic!(),
+        }
+    }
+    fn value(&self) -> Self::Value {
+        self.id().clone()
+    }
+    fn content(&self) -> Vec<String> {
+        vec![
+            format!("{}", self.id().0),
+            format!("{}", self.kind()),
+            format!("{}", self.reason()),
+        ]
+    }
+    fn column_names() -> Option<Vec<String>> {
+        Some(vec![format!("Feed"), format!("Error"), format!("Reason")])
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![Constraint::Length, Constraint::Length, Constraint::Fill]
+    }
+}
+
+#[derive(Debug)]
+pub enum CacheError {
+    Io(std::io::Error),
+    Db(rusqlite::Error),
+    Json(serde_json::Error),
+    Legacy(bincode::error::DecodeError),
+    Corrupted(String),
+    Version(usize),
+}
+impl CacheError {
+    pub fn is_corruption(&self) -> bool {
+        match self {
+            Self::Db(rusqlite::Error::SqliteFailure(e, _)) => matches!(
+                e.code,
+                rusqlite::ErrorCode::DatabaseCorrupt | rusqlite::ErrorCode::NotADatabase
+            ),
+            Self::Corrupted(_) => true,
+            Self::Io(_) | Self::Db(_) | Self::Json(_) | Self::Legacy(_) | Self::Version(_) => false,
+        }
+    }
+}
+impl Display for CacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "cache io error: {e}"),
+            Self::Db(e) => write!(f, "cache database error: {e}"),
+            Self::Json(e) => write!(f, "malformed cache entry: {e}"),
+            Self::Legacy(e) => write!(f, "failed to import the old cache: {e}"),
+            Self::Corrupted(e) => write!(f, "cache is corrupted: {e}"),
+            Self::Version(v) => write!(f, "cache version {v} was written by a newer rrss"),
+        }
+    }
+}
+impl std::error::Error for CacheError {}
+impl From<std::io::Error> for CacheError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+impl From<rusqlite::Error> for CacheError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Db(e)
+    }
+}
+impl From<serde_json::Error> for CacheError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+impl From<bincode::error::DecodeError> for CacheError {
+    fn from(e: bincode::error::DecodeError) -> Self {
+        Self::Legacy(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    pub file: String,
+    pub line: Option<usize>,
+    pub key: String,
+    pub reason: String,
+}
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        if !self.key.is_empty() {
+            write!(f, ": {}", self.key)?;
+        }
+        write!(f, ": {}", self.reason)
+    }
+}
+impl std::error::Error for ConfigError {}
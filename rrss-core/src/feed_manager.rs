@@ -1,25 +1,68 @@
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_semaphore::Semaphore;
 use async_std::channel::{Receiver, TryRecvError};
 use async_std::task::JoinHandle;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 use itertools::{Either, Itertools};
-use opml::OPML;
-use ratatui_helpers::config::parse_toml;
+use lazy_static::lazy_static;
+use regex::bytes::Regex;
+use ureq::http::StatusCode;
 
-use crate::cache::CachedFeeds;
-use crate::config::{PartialSources, Sources};
+use crate::cache::{CachedFeed, CachedFeeds};
+use crate::config::{FeedSource, SavedSearch, Sources, SourcesFile};
+use crate::discovery::{discover, FeedCandidate};
+use crate::error::{CacheError, ConfigError, FetchError};
 use crate::filter::{Filter, FilterTest};
-use crate::globals::{CONFIG, PROJECT_NAME, SOURCES_FILE};
-use crate::models::{Feed, FeedData, FeedId, Item, ItemId, Link, Tag};
+use crate::globals::{reload_config, CONFIG, PROJECT_NAME};
+use crate::models::{
+    Feed, FeedData, FeedHealth, FeedId, FeedRow, FeedState, Item, ItemId, ItemState, Link, Tag,
+    TimelineItem, VirtualFeed,
+};
+use crate::opml::{Opml, Outline};
+use crate::scheduler::Scheduler;
+use crate::search::{Query, SearchHit, SearchIndex};
 use crate::sorter::Sorter;
 
-type RequestError = Box<dyn std::error::Error + Send + Sync>;
-type FetchData = (FeedId, FeedData, usize);
-type FetchResult = Result<FetchData, RequestError>;
+pub type FetchResult = Result<FetchData, FetchError>;
+
+lazy_static! {
+    static ref UPDATE_PERIOD: Regex =
+        Regex::new(r"<sy:updatePeriod>\s*(\w+)\s*</sy:updatePeriod>").unwrap();
+    static ref UPDATE_FREQUENCY: Regex =
+        Regex::new(r"<sy:updateFrequency>\s*(\d+)\s*</sy:updateFrequency>").unwrap();
+}
+
+pub struct FetchRequest {
+    pub id: FeedId,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub timeout: Duration,
+}
+
+pub enum FetchOutcome {
+    Updated(Box<FeedData>),
+    Unchanged,
+}
+
+pub struct FetchData {
+    pub id: FeedId,
+    pub outcome: FetchOutcome,
+    pub bytes: usize,
+    pub status: u16,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedUpdate {
+    Updated,
+    Unchanged,
+}
 
 pub enum TaskStatus<T> {
     None,
@@ -30,29 +73,137 @@ pub enum TaskStatus<T> {
 
 pub struct FeedManager {
     feeds: Vec<Feed>,
-    save_mutex: Arc<Mutex<()>>,
+    searches: Vec<SavedSearch>,
+    cache: CachedFeeds,
+    index: SearchIndex,
     update_feeds_ch: Option<Receiver<Vec<FetchResult>>>,
     update_feed_ch: Option<Receiver<FetchResult>>,
+    discover_feeds_ch: Option<Receiver<Result<Vec<FeedCandidate>, FetchError>>>,
+    scheduler: Option<Scheduler>,
 }
 impl FeedManager {
-    #[allow(clippy::new_without_default)]
-    pub fn new() -> Self {
-        CachedFeeds::init();
-        let cached = CachedFeeds::load().unwrap();
-        let sources = parse_toml::<PartialSources, Sources>(PROJECT_NAME, SOURCES_FILE);
-        let fm = Self {
-            feeds: sources.to_feeds(cached),
-            save_mutex: Arc::new(Mutex::new(())),
+    pub fn new() -> Result<Self, CacheError> {
+        let (cache, cached) = CachedFeeds::open()?;
+        let (sources, _) = Sources::load();
+        let mut fm = Self {
+            feeds: vec![],
+            searches: vec![],
+            cache,
+            index: SearchIndex::new(),
             update_feeds_ch: None,
             update_feed_ch: None,
+            discover_feeds_ch: None,
+            scheduler: None,
         };
-        let _ = fm.save();
-        fm
+        fm.load_sources(sources, cached)?;
+        Ok(fm)
+    }
+    pub fn reload(&mut self) -> Result<Vec<ConfigError>, CacheError> {
+        let mut errors = reload_config();
+        let (sources, sources_errors) = Sources::load();
+        errors.extend(sources_errors);
+        // every change is saved right away, so the cache also holds what
+        // other processes like the daemon stored in the meantime
+        let cached = self.cache.load()?;
+        self.load_sources(sources, cached)?;
+        Ok(errors)
+    }
+    fn load_sources(&mut self, sources: Sources, feeds: Vec<CachedFeed>) -> Result<(), CacheError> {
+        let ids = feeds.iter().map(|f| f.id.clone()).collect_vec();
+        self.searches = sources.searches().to_vec();
+        self.feeds = sources.to_feeds(feeds);
+        self.save_all()?;
+        for id in ids {
+            if self.get_feed(id.clone()).is_none() {
+                self.cache.delete_feed(&id)?;
+            }
+        }
+        self.index = SearchIndex::new();
+        for feed in &self.feeds {
+            self.index.index_feed(feed);
+        }
+        Ok(())
     }
 
-    pub fn clear(&mut self) {
-        self.feeds.iter_mut().for_each(Feed::clear_data);
-        let _ = self.save();
+    pub fn cache_warning(&self) -> Option<&str> {
+        self.cache.warning()
+    }
+    pub fn clear(&mut self) -> Result<(), CacheError> {
+        for feed in &mut self.feeds {
+            feed.clear_data();
+            self.cache.clear_items(feed.id())?;
+        }
+        self.index = SearchIndex::new();
+        for feed in &self.feeds {
+            self.index.index_feed(feed);
+        }
+        self.save_all()
+    }
+    pub fn clear_feed(&mut self, id: FeedId) -> Result<(), CacheError> {
+        if let Some(feed) = self.feeds.iter_mut().find(|f| *f.id() == id) {
+            feed.clear_data();
+            self.cache.clear_items(&id)?;
+            self.index.index_feed(feed);
+            return self.save(&[id]);
+        }
+        Ok(())
+    }
+    pub fn add_feed(&mut self, source: FeedSource) -> Result<(), Box<dyn std::error::Error>> {
+        let mut sources = SourcesFile::open()?;
+        if sources.contains(&source.url) || self.get_feed(source.url.clone()).is_some() {
+            return Err(format!("already subscribed to {}", source.url.0).into());
+        }
+        sources.add(&source)?;
+        sources.save()?;
+        let id = source.url.clone();
+        self.feeds.push(Feed::new(source));
+        Ok(self.save(&[id])?)
+    }
+    pub fn remove_feed(&mut self, id: FeedId) -> Result<(), Box<dyn std::error::Error>> {
+        let mut sources = SourcesFile::open()?;
+        sources.remove(&id)?;
+        sources.save()?;
+        self.feeds.retain(|f| *f.id() != id);
+        self.index.remove_feed(&id);
+        Ok(self.cache.delete_feed(&id)?)
+    }
+    pub fn tag_feed(&mut self, id: FeedId, tag: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut sources = SourcesFile::open()?;
+        let _ = sources.add_tag(&id, tag)?;
+        sources.save()?;
+        if let Some(feed) = self.get_feed_mut(id) {
+            if !feed.conf.tags.iter().any(|t| t == tag) {
+                feed.conf.tags.push(tag.to_string());
+            }
+        }
+        Ok(())
+    }
+    pub fn set_tags(
+        &mut self,
+        id: FeedId,
+        tags: Vec<String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut sources = SourcesFile::open()?;
+        sources.set_tags(&id, &tags)?;
+        sources.save()?;
+        if let Some(feed) = self.get_feed_mut(id) {
+            feed.conf.tags = tags;
+        }
+        Ok(())
+    }
+    pub fn set_max_items(
+        &mut self,
+        id: FeedId,
+        max_items: u32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut sources = SourcesFile::open()?;
+        sources.set_max_items(&id, max_items)?;
+        sources.save()?;
+        if let Some(feed) = self.get_feed_mut(id.clone()) {
+            feed.conf.max_items = max_items;
+            feed.truncate_items();
+        }
+        Ok(self.save(&[id])?)
     }
     pub fn update_feed(
         &mut self,
@@ -60,11 +211,11 @@ impl FeedManager {
         finally: impl FnOnce() + Send + 'static,
     ) -> Option<JoinHandle<()>> {
         if let Some(feed) = self.get_feed(id) {
-            let url = feed.url();
+            let req = feed.fetch_request();
             let (sx, rx) = async_std::channel::bounded(1);
             self.update_feed_ch = Some(rx);
             let fetch_task = async_std::task::spawn(async move {
-                let res = Self::fetch_feed(url).await;
+                let res = Self::fetch_feed(req).await;
                 sx.send(res).await.unwrap();
                 finally();
             });
@@ -72,29 +223,41 @@ impl FeedManager {
         }
         None
     }
+    pub fn discover_feeds(
+        &mut self,
+        url: String,
+        finally: impl FnOnce() + Send + 'static,
+    ) -> JoinHandle<()> {
+        let (sx, rx) = async_std::channel::bounded(1);
+        self.discover_feeds_ch = Some(rx);
+        let timeout = CONFIG.get().fetch_timeout;
+        async_std::task::spawn(async move {
+            let res = async_std::task::spawn_blocking(move || discover(&url, timeout)).await;
+            sx.send(res).await.unwrap();
+            finally();
+        })
+    }
     pub fn update_feeds(
         &mut self,
         filter: &Filter,
         finally: impl FnOnce() + Send + 'static,
     ) -> JoinHandle<()> {
-        let urls = self
+        let reqs = self
             .get_feeds(filter, &Sorter::NONE)
             .iter()
             .filter(|f| !f.conf.manual_update)
-            .map(|f| f.url())
+            .map(|f| f.fetch_request())
             .collect();
 
         let (sx, rx) = async_std::channel::bounded(1);
         self.update_feeds_ch = Some(rx);
         async_std::task::spawn(async move {
-            let res = Self::fetch_feeds(urls).await;
+            let res = Self::fetch_feeds(reqs, CONFIG.get().update_deadline).await;
             sx.send(res).await.unwrap();
             finally();
         })
     }
-    pub fn poll_update_feeds(
-        &mut self,
-    ) -> TaskStatus<(Vec<RequestError>, std::thread::JoinHandle<()>)> {
+    pub fn poll_update_feeds(&mut self) -> TaskStatus<Vec<FetchError>> {
         match &self.update_feeds_ch {
             None => TaskStatus::None,
             Some(rx) => match rx.try_recv() {
@@ -105,16 +268,21 @@ impl FeedManager {
                 }
                 Ok(feeds) => {
                     self.update_feeds_ch = None;
-                    let (ok, err) = feeds
+                    let ids = feeds.iter().map(result_id).cloned().collect_vec();
+                    let (ok, err): (_, Vec<_>) = feeds
                         .into_iter()
                         .partition_map(|r| r.map_or_else(Either::Right, Either::Left));
                     self.merge_new_feeds(ok);
-                    TaskStatus::Done((err, self.save()))
+                    self.record_failures(&err);
+                    match self.save(&ids) {
+                        Ok(()) => TaskStatus::Done(err),
+                        Err(e) => TaskStatus::Error(e.to_string()),
+                    }
                 }
             },
         }
     }
-    pub fn poll_update_feed(&mut self) -> TaskStatus<()> {
+    pub fn poll_update_feed(&mut self) -> TaskStatus<FeedUpdate> {
         match &self.update_feed_ch {
             None => TaskStatus::None,
             Some(rx) => match rx.try_recv() {
@@ -125,47 +293,219 @@ impl FeedManager {
                 }
                 Ok(Err(e)) => {
                     self.update_feed_ch = None;
+                    self.record_failures(std::slice::from_ref(&e));
+                    let _ = self.save(&[e.id().clone()]);
                     TaskStatus::Error(e.to_string())
                 }
-                Ok(Ok((id, data, bytes))) => {
+                Ok(Ok(data)) => {
                     self.update_feed_ch = None;
-                    if let Some(old_feed) = self.get_feed_mut(id) {
-                        old_feed.merge_feed(data);
-                        old_feed.update_bytes(bytes);
-                        let _ = self.save();
+                    let id = data.id.clone();
+                    let update = self.apply_fetch(data);
+                    match self.save(&[id]) {
+                        Ok(()) => TaskStatus::Done(update),
+                        Err(e) => TaskStatus::Error(e.to_string()),
                     }
-                    TaskStatus::Done(())
                 }
             },
         }
     }
+    pub fn poll_discover_feeds(&mut self) -> TaskStatus<Vec<FeedCandidate>> {
+        match &self.discover_feeds_ch {
+            None => TaskStatus::None,
+            Some(rx) => match rx.try_recv() {
+                Err(TryRecvError::Empty) => TaskStatus::Running,
+                Err(TryRecvError::Closed) => {
+                    self.discover_feeds_ch = None;
+                    TaskStatus::Error("Internal error".into())
+                }
+                Ok(res) => {
+                    self.discover_feeds_ch = None;
+                    match res {
+                        Ok(candidates) => TaskStatus::Done(candidates),
+                        Err(e) => TaskStatus::Error(e.to_string()),
+                    }
+                }
+            },
+        }
+    }
+    pub fn start_scheduler(&mut self) {
+        if self.scheduler.is_none() {
+            self.scheduler = Some(Scheduler::new());
+        }
+    }
+    pub fn stop_scheduler(&mut self) {
+        self.scheduler = None;
+    }
+    pub fn poll_scheduler(&mut self) -> TaskStatus<(Vec<FeedId>, Vec<FetchError>)> {
+        let Some(scheduler) = &mut self.scheduler else {
+            return TaskStatus::None;
+        };
+        for feed in scheduler.due_feeds(self.feeds.iter()) {
+            let req = feed.fetch_request();
+            let semaphore = scheduler.semaphore();
+            let sx = scheduler.sender();
+            drop(async_std::task::spawn(async move {
+                let _guard = semaphore.acquire().await;
+                let _ = sx.send(Self::fetch_feed(req).await).await;
+            }));
+        }
+
+        let mut results = vec![];
+        while let Ok(res) = scheduler.receiver().try_recv() {
+            scheduler.finished(result_id(&res));
+            results.push(res);
+        }
+        if results.is_empty() {
+            return TaskStatus::Running;
+        }
+
+        let (ok, err): (Vec<_>, Vec<_>) = results
+            .into_iter()
+            .partition_map(|r| r.map_or_else(Either::Right, Either::Left));
+        let updated = ok.iter().map(|d| d.id.clone()).collect_vec();
+        let failed = err.iter().map(|e| e.id().clone()).collect_vec();
+        self.merge_new_feeds(ok);
+        self.record_failures(&err);
+        match self.save(&[updated.as_slice(), failed.as_slice()].concat()) {
+            Ok(()) => TaskStatus::Done((updated, err)),
+            Err(e) => TaskStatus::Error(e.to_string()),
+        }
+    }
     pub fn merge_new_feeds(&mut self, new_feeds: Vec<FetchData>) {
         for new in new_feeds {
-            if let Some(feed) = self.get_feed_mut(new.0) {
-                feed.merge_feed(new.1)
+            let _ = self.apply_fetch(new);
+        }
+    }
+    pub fn apply_fetch(&mut self, fetched: FetchData) -> FeedUpdate {
+        let Some(feed) = self.feeds.iter_mut().find(|f| *f.id() == fetched.id) else {
+            return FeedUpdate::Unchanged;
+        };
+        feed.update_bytes(fetched.bytes);
+        feed.record_success(fetched.status);
+        match fetched.outcome {
+            FetchOutcome::Updated(data) => {
+                feed.update_validators(fetched.etag, fetched.last_modified);
+                feed.state.last_size = fetched.bytes;
+                feed.merge_feed(*data);
+                self.index.index_feed(feed);
+                FeedUpdate::Updated
+            }
+            FetchOutcome::Unchanged => {
+                if fetched.etag.is_some() || fetched.last_modified.is_some() {
+                    feed.update_validators(fetched.etag, fetched.last_modified);
+                }
+                feed.state.saved_bytes += feed.state.last_size;
+                FeedUpdate::Unchanged
             }
         }
     }
-    pub fn mark_item_as_read(&mut self, id: ItemId) -> Option<std::thread::JoinHandle<()>> {
-        if let Some(i) = self.get_item_mut(id) {
-            i.state.read_on = Some(Utc::now());
-            return Some(self.save());
+    pub fn record_failures(&mut self, errs: &[FetchError]) {
+        for e in errs {
+            if let Some(feed) = self.get_feed_mut(e.id().clone()) {
+                feed.record_failure(e);
+            }
         }
-        None
     }
-    pub fn mark_feed_as_read(&mut self, id: FeedId) -> std::thread::JoinHandle<()> {
+    pub fn mark_item_as_read(&mut self, id: ItemId) -> Result<(), CacheError> {
+        let unread = Filter::new().item_id(id.clone()).unread_item();
+        if let Some(i) = self.items_mut(&unread).into_iter().next() {
+            let now = Utc::now();
+            i.state.read_on = Some(now);
+            return self.cache.set_read_on(&id, Some(now));
+        }
+        Ok(())
+    }
+    pub fn mark_item_as_unread(&mut self, id: ItemId) -> Result<(), CacheError> {
+        if let Some(i) = self.get_item_mut(id.clone()) {
+            i.state.read_on = None;
+            return self.cache.set_read_on(&id, None);
+        }
+        Ok(())
+    }
+    pub fn toggle_item_read(&mut self, id: ItemId) -> Result<(), CacheError> {
+        match self.get_item(id.clone()).map(|i| i.state.read_on.is_some()) {
+            Some(true) => self.mark_item_as_unread(id),
+            Some(false) => self.mark_item_as_read(id),
+            None => Ok(()),
+        }
+    }
+    pub fn toggle_item_starred(&mut self, id: ItemId) -> Result<(), CacheError> {
+        if let Some(i) = self.get_item_mut(id.clone()) {
+            i.state.starred = !i.state.starred;
+            let starred = i.state.starred;
+            return self.cache.set_starred(&id, starred);
+        }
+        Ok(())
+    }
+    pub fn mark_feed_as_read(&mut self, id: FeedId) -> Result<(), CacheError> {
         let now = Utc::now();
-        self.items_mut(&Filter::new().feed_id(id))
+        self.items_mut(&Filter::new().feed_id(id.clone()).unread_item())
             .iter_mut()
             .for_each(|i| i.state.read_on = Some(now));
-        self.save()
+        self.save(&[id])
     }
-    pub fn increment_feed_hits(&mut self, id: &FeedId) -> Option<std::thread::JoinHandle<()>> {
-        if let Some(feed) = self.get_feed_mut(id.clone()) {
+    pub fn mark_items_as_read(&mut self, filter: &Filter) -> Result<(), CacheError> {
+        let ids = self
+            .items(filter)
+            .into_iter()
+            .filter(|i| i.state.read_on.is_none())
+            .map(|i| i.data.id.clone())
+            .collect::<HashSet<_>>();
+        let now = Utc::now();
+        let mut touched = vec![];
+        for feed in &mut self.feeds {
+            let items = feed.items_mut().into_iter().flatten();
+            let marked = items
+                .filter(|i| ids.contains(&i.data.id))
+                .map(|i| i.state.read_on = Some(now))
+                .count();
+            if marked > 0 {
+                touched.push(feed.id().clone());
+            }
+        }
+        self.save(&touched)
+    }
+    pub fn set_items_state(&mut self, states: Vec<(ItemId, ItemState)>) -> Result<(), CacheError> {
+        let states = states.into_iter().collect::<HashMap<_, _>>();
+        let mut touched = vec![];
+        for feed in &mut self.feeds {
+            let items = feed.items_mut().into_iter().flatten();
+            let restored = items
+                .filter_map(|i| Some((states.get(&i.data.id)?, i)))
+                .map(|(state, i)| i.state = state.clone())
+                .count();
+            if restored > 0 {
+                touched.push(feed.id().clone());
+            }
+        }
+        self.save(&touched)
+    }
+    pub fn restore_feed(
+        &mut self,
+        id: FeedId,
+        state: FeedState,
+        data: Option<FeedData>,
+    ) -> Result<(), CacheError> {
+        if let Some(feed) = self.feeds.iter_mut().find(|f| *f.id() == id) {
+            feed.state = state;
+            feed.data = data;
+            self.index.index_feed(feed);
+            return self.save(&[id]);
+        }
+        Ok(())
+    }
+    pub fn mark_tag_as_read(&mut self, tag: String) -> Result<(), CacheError> {
+        self.mark_items_as_read(&Filter::new().tag_id(tag))
+    }
+    pub fn mark_items_older_than_as_read(&mut self, date: DateTime<Utc>) -> Result<(), CacheError> {
+        self.mark_items_as_read(&Filter::new().posted_before(date))
+    }
+    pub fn increment_feed_hits(&mut self, id: &FeedId) -> Result<(), CacheError> {
+        if let Some(feed) = self.feeds.iter_mut().find(|f| f.id() == id) {
             feed.increment_hits();
-            return Some(self.save());
+            return self.cache.save_feed_state(feed);
         }
-        None
+        Ok(())
     }
     pub fn get_tags(&self, filter: &Filter, sorter: &Sorter<Tag>) -> Vec<Tag> {
         self.feeds(&Filter::new())
@@ -181,6 +521,38 @@ impl FeedManager {
             .sorted_by(|a, b| sorter.sort(a, b))
             .collect()
     }
+    pub fn get_search(&self, name: &str) -> Option<&SavedSearch> {
+        self.searches.iter().find(|s| s.name == name)
+    }
+    pub fn get_virtual_feeds(&self) -> Vec<VirtualFeed> {
+        self.searches
+            .iter()
+            .map(|s| {
+                let items = self.items(&s.filter.clone().visible());
+                VirtualFeed {
+                    name: s.name.clone(),
+                    tot_unread: items.iter().filter(|i| i.state.read_on.is_none()).count(),
+                    tot_items: items.len(),
+                    latest_item_date: items.iter().filter_map(|i| i.data.posted).max(),
+                }
+            })
+            .collect()
+    }
+    pub fn get_feed_rows(&self, filter: &Filter, sorter: &Sorter<Feed>) -> Vec<FeedRow> {
+        let virtual_feeds = match filter.tag_id.is_none() && filter.query.is_none() {
+            true => self.get_virtual_feeds(),
+            false => vec![],
+        };
+        virtual_feeds
+            .into_iter()
+            .map(FeedRow::Virtual)
+            .chain(
+                self.get_feeds(filter, sorter)
+                    .into_iter()
+                    .map(|f| FeedRow::Feed(Box::new(f))),
+            )
+            .collect()
+    }
     pub fn get_feeds(&self, filter: &Filter, sorter: &Sorter<Feed>) -> Vec<Feed> {
         self.feeds(filter)
             .into_iter()
@@ -188,6 +560,13 @@ impl FeedManager {
             .sorted_unstable_by(sorter.0)
             .collect()
     }
+    pub fn get_health(&self, filter: &Filter, sorter: &Sorter<Feed>) -> Vec<FeedHealth> {
+        self.feeds(filter)
+            .into_iter()
+            .sorted_by(|a, b| sorter.sort(a, b))
+            .map(FeedHealth::from)
+            .collect()
+    }
     pub fn get_items(&self, filter: &Filter, sorter: &Sorter<Item>) -> Vec<Item> {
         self.items(filter)
             .into_iter()
@@ -195,6 +574,41 @@ impl FeedManager {
             .sorted_by(sorter.0)
             .collect()
     }
+    pub fn get_timeline(&self, filter: &Filter, sorter: &Sorter<Item>) -> Vec<TimelineItem> {
+        let names = self
+            .feeds(filter)
+            .into_iter()
+            .map(|f| (f.id().clone(), f.name()))
+            .collect::<HashMap<_, _>>();
+        self.get_items(filter, sorter)
+            .into_iter()
+            .map(|item| TimelineItem {
+                feed: names
+                    .get(&FeedId(item.data.id.0.clone()))
+                    .cloned()
+                    .unwrap_or_default(),
+                item,
+            })
+            .collect()
+    }
+    pub fn search(&self, query: &str) -> Vec<SearchHit> {
+        let query = Query::parse(query);
+        let scores = self
+            .index
+            .search(&query)
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        self.feeds
+            .iter()
+            .flat_map(|f| f.items().into_iter().flatten().map(move |i| (f, i)))
+            .filter(|(_, i)| !i.state.effects.hidden)
+            .filter_map(|(f, i)| {
+                let score = *scores.get(&i.data.id)?;
+                Some(SearchHit::new(i.clone(), f.name(), &query, score))
+            })
+            .sorted_by(|a, b| b.score.total_cmp(&a.score))
+            .collect()
+    }
     pub fn get_links(&self, filter: &Filter, sorter: &Sorter<Link>) -> Vec<Link> {
         self.items(filter)
             .into_iter()
@@ -218,10 +632,29 @@ impl FeedManager {
             .into_iter()
             .next()
     }
-    pub fn as_opml(&self) -> OPML {
-        let mut opml = OPML::default();
+    pub fn as_opml(&self) -> Opml {
+        let mut opml = Opml {
+            title: Some(PROJECT_NAME.to_string()),
+            outlines: vec![],
+        };
         for feed in &self.feeds {
-            opml.add_feed(&feed.name(), &feed.url());
+            let outline = feed.conf.to_outline(&feed.name(), &feed.feed_type());
+            let Some(tag) = feed.conf.tags.first() else {
+                opml.outlines.push(outline);
+                continue;
+            };
+            let folder = opml
+                .outlines
+                .iter_mut()
+                .find(|o| o.attr("xmlUrl").is_none() && o.attr("text") == Some(tag));
+            match folder {
+                Some(folder) => folder.outlines.push(outline),
+                None => {
+                    let mut folder = Outline::new(tag).with("title", tag);
+                    folder.outlines.push(outline);
+                    opml.outlines.push(folder);
+                }
+            }
         }
         opml
     }
@@ -230,8 +663,9 @@ impl FeedManager {
         self.feeds
             .iter()
             .filter(|f| filter.test(*f))
-            .filter_map(Feed::items)
-            .flat_map(|items| items.iter().filter(|i| filter.test(*i)))
+            .flat_map(|f| f.items().into_iter().flatten().map(move |i| (f, i)))
+            .filter(|e| filter.test(e))
+            .map(|(_, i)| i)
             .collect()
     }
     fn items_mut(&mut self, filter: &Filter) -> Vec<&mut Item> {
@@ -248,41 +682,383 @@ impl FeedManager {
     fn feeds_mut(&mut self, filter: &Filter) -> Vec<&mut Feed> {
         self.feeds.iter_mut().filter(|f| filter.test(*f)).collect()
     }
-    fn save(&self) -> std::thread::JoinHandle<()> {
-        std::thread::spawn({
-            let guard = self.save_mutex.clone();
-            let feeds = self.feeds.clone();
-            move || {
-                let _guard = guard.lock();
-                CachedFeeds::save(&feeds).unwrap();
-            }
-        })
+    fn save(&mut self, ids: &[FeedId]) -> Result<(), CacheError> {
+        let feeds = self
+            .feeds
+            .iter()
+            .filter(|f| ids.contains(f.id()))
+            .collect_vec();
+        self.cache.save_feeds(&feeds)
+    }
+    fn save_all(&mut self) -> Result<(), CacheError> {
+        self.cache.save_feeds(&self.feeds.iter().collect_vec())
     }
 
-    async fn fetch_feed(url: String) -> FetchResult {
-        async_std::task::spawn_blocking(move || fetch_feed_impl(&url)).await
+    async fn fetch_feed(req: FetchRequest) -> FetchResult {
+        async_std::task::spawn_blocking(move || fetch_feed_impl(req)).await
     }
-    async fn fetch_feeds(urls: Vec<String>) -> Vec<FetchResult> {
-        let semaphore = Arc::new(Semaphore::new(CONFIG.max_concurrency));
-        let futures = FuturesUnordered::new();
-        for url in urls {
+    async fn fetch_feeds(reqs: Vec<FetchRequest>, deadline: Duration) -> Vec<FetchResult> {
+        let semaphore = Arc::new(Semaphore::new(CONFIG.get().max_concurrency));
+        let mut pending: HashSet<FeedId> = reqs.iter().map(|r| r.id.clone()).collect();
+        let mut futures = FuturesUnordered::new();
+        let until = Instant::now() + deadline;
+        for mut req in reqs {
             let future = async_std::task::spawn({
                 let semaphore = semaphore.clone();
                 async move {
                     let _guard = semaphore.acquire().await;
-                    Self::fetch_feed(url).await
+                    let id = req.id.clone();
+                    // don't start requests nobody is waiting for anymore
+                    let remaining = until.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return (id.clone(), Err(FetchError::Timeout(id, deadline)));
+                    }
+                    req.timeout = req.timeout.min(remaining);
+                    (id, Self::fetch_feed(req).await)
                 }
             });
             futures.push(future);
         }
-        futures.collect().await
+
+        let mut results = Vec::with_capacity(pending.len());
+        while let Ok(Some((id, res))) = async_std::future::timeout(
+            until.saturating_duration_since(Instant::now()),
+            futures.next(),
+        )
+        .await
+        {
+            let _ = pending.remove(&id);
+            results.push(res);
+        }
+        results.extend(
+            pending
+                .into_iter()
+                .map(|id| Err(FetchError::Timeout(id, deadline))),
+        );
+        results
     }
 }
 
-fn fetch_feed_impl(url: &str) -> FetchResult {
-    let data = ureq::get(url).call()?.into_body().read_to_string()?;
-    let data = data.as_bytes();
+fn fetch_feed_impl(req: FetchRequest) -> FetchResult {
+    let url = req.id.0.as_str();
+    let mut request = ureq::get(url)
+        .config()
+        .timeout_global(Some(req.timeout))
+        .build();
+    if let Some(etag) = &req.etag {
+        request = request.header("If-None-Match", etag);
+    }
+    if let Some(last_modified) = &req.last_modified {
+        request = request.header("If-Modified-Since", last_modified);
+    }
+    let res = request
+        .call()
+        .map_err(|e| FetchError::from_request(req.id.clone(), req.timeout, e))?;
+    let etag = header_value(&res, "ETag");
+    let last_modified = header_value(&res, "Last-Modified");
+
+    if res.status() == StatusCode::NOT_MODIFIED {
+        return Ok(FetchData {
+            id: req.id,
+            outcome: FetchOutcome::Unchanged,
+            bytes: 0,
+            status: StatusCode::NOT_MODIFIED.as_u16(),
+            etag,
+            last_modified,
+        });
+    }
+
+    let status = res.status().as_u16();
+    let data = res
+        .into_body()
+        .read_to_vec()
+        .map_err(|e| FetchError::from_request(req.id.clone(), req.timeout, e))?;
     let bytes = data.len();
-    let data = feed_rs::parser::parse(data).map(|d| FeedData::from(d, url))?;
-    Ok((FeedId(url.to_string()), data, bytes))
+    let ttl = syndication_ttl(&data);
+    let mut data = feed_rs::parser::parse(data.as_slice())
+        .map(|d| FeedData::from(d, url))
+        .map_err(|e| FetchError::from_parse(req.id.clone(), e))?;
+    data.ttl = data.ttl.or(ttl);
+    Ok(FetchData {
+        id: req.id,
+        outcome: FetchOutcome::Updated(Box::new(data)),
+        bytes,
+        status,
+        etag,
+        last_modified,
+    })
+}
+
+fn syndication_ttl(body: &[u8]) -> Option<u32> {
+    let period = UPDATE_PERIOD.captures(body)?;
+    let minutes = match &period[1] {
+        b"hourly" => 60,
+        b"daily" => 60 * 24,
+        b"weekly" => 60 * 24 * 7,
+        b"monthly" => 60 * 24 * 30,
+        b"yearly" => 60 * 24 * 365,
+        _ => return None,
+    };
+    let frequency = UPDATE_FREQUENCY
+        .captures(body)
+        .and_then(|c| std::str::from_utf8(&c[1]).ok()?.parse::<u32>().ok())
+        .unwrap_or(1)
+        .max(1);
+    Some(minutes / frequency)
+}
+
+fn result_id(res: &FetchResult) -> &FeedId {
+    match res {
+        Ok(data) => &data.id,
+        Err(e) => e.id(),
+    }
+}
+
+fn header_value<B>(res: &ureq::http::Response<B>, name: &str) -> Option<String> {
+    res.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::globals::CACHE_FILE;
+
+    const RSS: &str = r#"<?xml version="1.0"?><rss version="2.0"><channel><title>Rss</title><link>http://x</link><description>d</description><item><guid>a</guid><title>a</title></item><item><guid>b</guid><title>b</title></item></channel></rss>"#;
+    const VALIDATORS: [&str; 2] = [
+        "ETag: \"v1\"",
+        "Last-Modified: Mon, 01 Jan 2024 00:00:00 GMT",
+    ];
+
+    // answers every request with `respond(head)` after the given delay,
+    // where head is the lowercased request line and headers
+    fn serve(respond: fn(&str) -> (u64, Vec<u8>)) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _ = std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = std::thread::spawn(move || {
+                    let mut head = String::new();
+                    let mut reader = BufReader::new(&stream);
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                        head.push_str(&line.to_lowercase());
+                        line.clear();
+                    }
+                    let (delay, res) = respond(&head);
+                    std::thread::sleep(Duration::from_millis(delay));
+                    let _ = stream.write_all(&res);
+                });
+            }
+        });
+        format!("http://{addr}")
+    }
+    fn response(status: &str, headers: &[&str], body: &[u8]) -> Vec<u8> {
+        let mut res = format!("HTTP/1.1 {status}\r\n");
+        for header in headers {
+            res.push_str(&format!("{header}\r\n"));
+        }
+        res.push_str(&format!(
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        ));
+        [res.as_bytes(), body].concat()
+    }
+    fn path(head: &str) -> &str {
+        head.split_whitespace().nth(1).unwrap_or("")
+    }
+    fn request(url: String, timeout: u64) -> FetchRequest {
+        FetchRequest {
+            id: FeedId(url),
+            etag: None,
+            last_modified: None,
+            timeout: Duration::from_millis(timeout),
+        }
+    }
+    fn manager(name: &str, feeds: Vec<Feed>) -> FeedManager {
+        let dir = std::env::temp_dir().join(format!("rrss-fm-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let (cache, _) = CachedFeeds::open_at(&dir.join(CACHE_FILE)).unwrap();
+        FeedManager {
+            feeds,
+            searches: vec![],
+            cache,
+            index: SearchIndex::new(),
+            update_feeds_ch: None,
+            update_feed_ch: None,
+            discover_feeds_ch: None,
+            scheduler: None,
+        }
+    }
+
+    #[test]
+    fn not_modified_keeps_items_and_counts_saved_bytes() {
+        let url = serve(|head| {
+            let valid = head.contains("if-none-match: \"v1\"")
+                && head.contains("if-modified-since: mon, 01 jan 2024 00:00:00 gmt");
+            match valid {
+                true => (0, response("304 Not Modified", &VALIDATORS, b"")),
+                false => (0, response("200 OK", &VALIDATORS, RSS.as_bytes())),
+            }
+        });
+        let id = FeedId(format!("{url}/feed"));
+        let mut fm = manager(
+            "not-modified",
+            vec![Feed::new(FeedSource::new(id.clone(), vec![]))],
+        );
+        let fetch = |fm: &FeedManager| {
+            let req = fm.get_feed(id.clone()).unwrap().fetch_request();
+            fetch_feed_impl(req).unwrap()
+        };
+
+        let first = fetch(&fm);
+        assert_eq!(first.status, 200);
+        assert_eq!(fm.apply_fetch(first), FeedUpdate::Updated);
+        let second = fetch(&fm);
+        assert_eq!(second.status, 304);
+        assert!(matches!(second.outcome, FetchOutcome::Unchanged));
+        assert_eq!(fm.apply_fetch(second), FeedUpdate::Unchanged);
+
+        let feed = fm.get_feed(id).unwrap();
+        assert_eq!(feed.state.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(
+            feed.state.last_modified.as_deref(),
+            Some("Mon, 01 Jan 2024 00:00:00 GMT")
+        );
+        assert_eq!(feed.state.last_size, RSS.len());
+        assert_eq!(feed.state.saved_bytes, RSS.len());
+        assert_eq!(feed.data.as_ref().unwrap().items.len(), 2);
+    }
+
+    #[test]
+    fn edited_items_are_updated_and_reindexed() {
+        static FETCHES: AtomicUsize = AtomicUsize::new(0);
+        let url = serve(|_| match FETCHES.fetch_add(1, Ordering::SeqCst) {
+            0 => (0, response("200 OK", &[], RSS.as_bytes())),
+            _ => {
+                let edited = RSS.replace("<title>a</title>", "<title>renamed</title>");
+                (0, response("200 OK", &[], edited.as_bytes()))
+            }
+        });
+        let id = FeedId(format!("{url}/feed"));
+        let mut fm = manager(
+            "edited",
+            vec![Feed::new(FeedSource::new(id.clone(), vec![]))],
+        );
+        let fetch = |fm: &mut FeedManager| {
+            let req = fm.get_feed(id.clone()).unwrap().fetch_request();
+            let _ = fm.apply_fetch(fetch_feed_impl(req).unwrap());
+            fm.save(std::slice::from_ref(&id)).unwrap();
+        };
+
+        fetch(&mut fm);
+        let item = ItemId(id.0.clone(), "a".into());
+        fm.mark_item_as_read(item.clone()).unwrap();
+        fetch(&mut fm);
+
+        let items = fm.get_feed(id.clone()).unwrap().items().unwrap();
+        assert_eq!(items.len(), 2);
+        let edited = items.iter().find(|i| i.data.id == item).unwrap();
+        assert_eq!(edited.data.title.as_deref(), Some("renamed"));
+        assert!(edited.state.read_on.is_some());
+        assert!(fm.search("a").iter().all(|h| h.item.data.id != item));
+        assert_eq!(fm.search("renamed")[0].item.data.id, item);
+
+        let dir = std::env::temp_dir().join(format!("rrss-fm-edited-{}", std::process::id()));
+        let (_, cached) = CachedFeeds::open_at(&dir.join(CACHE_FILE)).unwrap();
+        let items = cached[0].data.as_ref().unwrap().items.clone();
+        let cached = items.iter().find(|i| i.data.id == item).unwrap();
+        assert_eq!(cached.data.title.as_deref(), Some("renamed"));
+        assert!(cached.state.read_on.is_some());
+    }
+
+    #[test]
+    fn feeds_pending_at_the_deadline_time_out() {
+        let url = serve(|head| match path(head) {
+            "/slow" => (3000, response("200 OK", &[], RSS.as_bytes())),
+            _ => (0, response("200 OK", &[], RSS.as_bytes())),
+        });
+        let reqs = vec![
+            request(format!("{url}/fast"), 10_000),
+            request(format!("{url}/slow"), 10_000),
+        ];
+        let deadline = Duration::from_millis(500);
+        let res = async_std::task::block_on(FeedManager::fetch_feeds(reqs, deadline));
+        let (done, errs): (Vec<_>, Vec<_>) = res.into_iter().partition_result();
+
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0].id, FeedId(format!("{url}/fast")));
+        match &errs[..] {
+            [FetchError::Timeout(id, after)] => {
+                assert_eq!(*id, FeedId(format!("{url}/slow")));
+                assert_eq!(*after, deadline);
+            }
+            _ => panic!("expected a single timeout, got {errs:?}"),
+        }
+    }
+
+    #[test]
+    fn queued_feeds_are_not_fetched_after_the_deadline() {
+        static HITS: AtomicUsize = AtomicUsize::new(0);
+        let url = serve(|_| {
+            let _ = HITS.fetch_add(1, Ordering::SeqCst);
+            (3000, response("200 OK", &[], RSS.as_bytes()))
+        });
+        let concurrency = CONFIG.get().max_concurrency;
+        let reqs = (0..=concurrency)
+            .map(|i| request(format!("{url}/{i}"), 10_000))
+            .collect();
+        let deadline = Duration::from_millis(300);
+        let res = async_std::task::block_on(FeedManager::fetch_feeds(reqs, deadline));
+        assert_eq!(res.len(), concurrency + 1);
+        assert!(res
+            .iter()
+            .all(|r| matches!(r, Err(FetchError::Timeout(..)))));
+
+        // in-flight requests give up at the deadline and free their slots,
+        // but the queued one must not reach the server
+        std::thread::sleep(Duration::from_millis(1000));
+        assert_eq!(HITS.load(Ordering::SeqCst), concurrency);
+    }
+
+    #[test]
+    fn fetch_errors_are_classified() {
+        let url = serve(|head| match path(head) {
+            "/slow" => (3000, response("200 OK", &[], RSS.as_bytes())),
+            "/html" => (0, response("200 OK", &[], b"<html><body>hi</body></html>")),
+            "/latin1" => (
+                0,
+                response(
+                    "200 OK",
+                    &[],
+                    b"<rss version=\"2.0\">caf\xe9<channel></channel></rss>",
+                ),
+            ),
+            _ => (0, response("404 Not Found", &[], b"")),
+        });
+        // a port nothing listens on anymore
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let fetch = |url: String, timeout| fetch_feed_impl(request(url, timeout)).err().unwrap();
+
+        let err = fetch(format!("http://{closed}/feed"), 10_000);
+        assert_eq!(err.kind(), "connect");
+        let err = fetch(format!("{url}/missing"), 10_000);
+        assert_eq!(err.kind(), "status");
+        assert_eq!(err.status(), Some(404));
+        let err = fetch(format!("{url}/slow"), 300);
+        assert_eq!(err.kind(), "timeout");
+        assert_eq!(fetch(format!("{url}/html"), 10_000).kind(), "parse");
+        assert_eq!(fetch(format!("{url}/latin1"), 10_000).kind(), "encoding");
+    }
 }
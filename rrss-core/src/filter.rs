@@ -1,3 +1,8 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use regex::{Regex, RegexBuilder};
+
 use super::models::{Feed, FeedId, Item, ItemId, Tag};
 
 pub trait FilterTest<T> {
@@ -12,17 +17,25 @@ pub struct Filter {
 
     pub unread_feed: Option<bool>,
     pub unread_item: Option<bool>,
+    pub starred: Option<bool>,
 
     pub feed_contains: Option<String>,
     pub item_contains: Option<String>,
     pub tag_contains: Option<String>,
 
     pub unfiltered: Option<()>,
+    pub unhealthy: Option<()>,
+    pub visible: Option<()>,
+
+    pub query: Option<Expr>,
 }
 impl Filter {
     pub fn new() -> Self {
         Self::default()
     }
+    pub fn parse(query: &str) -> Result<Self, ParseError> {
+        Ok(Self::new().query(Expr::parse(query)?))
+    }
     pub fn tag_id(mut self, tag: String) -> Self {
         self.tag_id = Some(tag);
         self
@@ -51,12 +64,16 @@ impl Filter {
         self.unread_item = Some(false);
         self
     }
+    pub fn starred(mut self) -> Self {
+        self.starred = Some(true);
+        self
+    }
     pub fn feed_contains(mut self, pattern: String) -> Self {
         self.feed_contains = Some(pattern);
         self
     }
     pub fn item_contains(mut self, pattern: String) -> Self {
-        self.feed_contains = Some(pattern);
+        self.item_contains = Some(pattern);
         self
     }
     pub fn tag_contains(mut self, pattern: String) -> Self {
@@ -67,27 +84,34 @@ impl Filter {
         self.unfiltered = Some(());
         self
     }
-}
-impl FilterTest<Feed> for Filter {
-    fn test(&self, e: &Feed) -> bool {
-        let mut test = true;
-        if let Some(tag) = &self.tag_id {
-            test = test && e.conf.tags.contains(tag);
-        }
-        if let Some(id) = &self.feed_id {
-            test = test && e.id() == id;
-        }
-        if self.unread_feed.is_some() {
-            test = test && e.tot_unread() > 0;
-        }
-        if let Some(p) = &self.feed_contains {
-            test = test && e.name().to_ascii_lowercase().contains(&p.to_lowercase());
-        }
-        test
+    pub fn unhealthy(mut self) -> Self {
+        self.unhealthy = Some(());
+        self
     }
-}
-impl FilterTest<Item> for Filter {
-    fn test(&self, e: &Item) -> bool {
+    // leaves out the items hidden by a rule, only listings should use it so
+    // that marking as read still reaches them
+    pub fn visible(mut self) -> Self {
+        self.visible = Some(());
+        self
+    }
+    pub fn posted_before(self, date: DateTime<Utc>) -> Self {
+        self.and_query(Expr::Pred(Predicate::Posted {
+            from: None,
+            to: Some(date),
+        }))
+    }
+    pub fn query(mut self, expr: Expr) -> Self {
+        self.query = Some(expr);
+        self
+    }
+    pub fn and_query(mut self, expr: Expr) -> Self {
+        self.query = Some(match self.query {
+            Some(q) => Expr::And(Box::new(q), Box::new(expr)),
+            None => expr,
+        });
+        self
+    }
+    fn test_item(&self, e: &Item, feed: Option<&Feed>) -> bool {
         let mut test = true;
         if let Some(id) = &self.item_id {
             test = test && e.data.id == *id;
@@ -98,6 +122,9 @@ impl FilterTest<Item> for Filter {
         if let Some(false) = self.unread_item {
             test = test && e.state.read_on.is_some();
         }
+        if let Some(starred) = self.starred {
+            test = test && e.state.starred == starred;
+        }
         if let Some(p) = &self.item_contains {
             if let Some(title) = &e.data.title {
                 test = test && title.to_lowercase().contains(&p.to_lowercase());
@@ -110,9 +137,69 @@ impl FilterTest<Item> for Filter {
         if self.unfiltered.is_some() {
             test = test && !e.state.is_filtered;
         }
+        if self.visible.is_some() {
+            test = test && !e.state.effects.hidden;
+        }
+        if let Some(q) = &self.query {
+            let ctx = Ctx {
+                feed,
+                item: Some(e),
+                tag: None,
+            };
+            test = test && q.eval(&ctx).unwrap_or(true);
+        }
+        test
+    }
+}
+impl FilterTest<Feed> for Filter {
+    fn test(&self, e: &Feed) -> bool {
+        let mut test = true;
+        if let Some(tag) = &self.tag_id {
+            test = test && e.conf.tags.contains(tag);
+        }
+        if let Some(id) = &self.feed_id {
+            test = test && e.id() == id;
+        }
+        if self.unread_feed.is_some() {
+            test = test && e.tot_unread() > 0;
+        }
+        if let Some(p) = &self.feed_contains {
+            test = test && e.name().to_ascii_lowercase().contains(&p.to_lowercase());
+        }
+        if self.unhealthy.is_some() {
+            test = test && !e.is_healthy();
+        }
+        if let Some(q) = &self.query {
+            let ctx = Ctx {
+                feed: Some(e),
+                item: None,
+                tag: None,
+            };
+            test = test
+                && match q.eval(&ctx) {
+                    Some(test) => test,
+                    None => e.items().into_iter().flatten().any(|i| {
+                        let ctx = Ctx {
+                            item: Some(i),
+                            ..ctx
+                        };
+                        q.eval(&ctx) == Some(true)
+                    }),
+                };
+        }
         test
     }
 }
+impl FilterTest<Item> for Filter {
+    fn test(&self, e: &Item) -> bool {
+        self.test_item(e, None)
+    }
+}
+impl FilterTest<(&Feed, &Item)> for Filter {
+    fn test(&self, (feed, item): &(&Feed, &Item)) -> bool {
+        self.test_item(item, Some(feed))
+    }
+}
 impl FilterTest<Tag> for Filter {
     fn test(&self, e: &Tag) -> bool {
         let mut test = true;
@@ -122,6 +209,14 @@ impl FilterTest<Tag> for Filter {
         if let Some(p) = &self.tag_contains {
             test = test && e.name.to_ascii_lowercase().contains(&p.to_lowercase());
         }
+        if let Some(q) = &self.query {
+            let ctx = Ctx {
+                feed: None,
+                item: None,
+                tag: Some(e),
+            };
+            test = test && q.eval(&ctx).unwrap_or(true);
+        }
         test
     }
 }
@@ -130,3 +225,675 @@ impl std::fmt::Display for Filter {
         write!(f, "Filters: - {:?}", self)
     }
 }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Pred(Predicate),
+}
+impl Expr {
+    pub fn parse(query: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            tokens: lex(query)?,
+            pos: 0,
+            end: query.len(),
+        };
+        let expr = parser.or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some((pos, token)) => Err(ParseError::new(*pos, format!("unexpected {token}"))),
+        }
+    }
+    fn eval(&self, ctx: &Ctx) -> Option<bool> {
+        match self {
+            Expr::And(a, b) => match (a.eval(ctx), b.eval(ctx)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+            Expr::Or(a, b) => match (a.eval(ctx), b.eval(ctx)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+            Expr::Not(e) => e.eval(ctx).map(|b| !b),
+            Expr::Pred(p) => p.eval(ctx),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Unread,
+    Read,
+    Starred,
+    Filtered,
+    Unhealthy,
+    Tag(Matcher),
+    Feed(Matcher),
+    Url(Matcher),
+    Title(Matcher),
+    Content(Matcher),
+    Author(Matcher),
+    Link(Matcher),
+    Posted {
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    },
+    Text(Matcher),
+}
+impl Predicate {
+    fn eval(&self, ctx: &Ctx) -> Option<bool> {
+        let Ctx { feed, item, tag } = *ctx;
+        match self {
+            Predicate::Unread => item.map(|i| i.state.read_on.is_none()),
+            Predicate::Read => item.map(|i| i.state.read_on.is_some()),
+            Predicate::Starred => item.map(|i| i.state.starred),
+            Predicate::Filtered => item.map(|i| i.state.is_filtered),
+            Predicate::Unhealthy => feed.map(|f| !f.is_healthy()),
+            Predicate::Tag(m) => match (feed, item, tag) {
+                (Some(f), _, _) if f.conf.tags.iter().any(|t| m.is_exact(t)) => Some(true),
+                (_, Some(i), _) => Some(i.state.effects.tags.iter().any(|t| m.is_exact(t))),
+                (None, None, Some(t)) => Some(m.is_exact(&t.name)),
+                _ => None,
+            },
+            Predicate::Feed(m) => feed.map(|f| m.is_match(&f.name())),
+            Predicate::Url(m) => match (feed, item) {
+                (Some(f), _) => Some(m.is_match(&f.id().0)),
+                (None, Some(i)) => Some(m.is_match(&i.data.id.0)),
+                _ => None,
+            },
+            Predicate::Title(m) => {
+                item.map(|i| i.data.title.as_ref().is_some_and(|t| m.is_match(t)))
+            }
+            Predicate::Content(m) => item.map(|i| {
+                [&i.data.summary, &i.data.content]
+                    .into_iter()
+                    .flatten()
+                    .any(|t| m.is_match(t))
+            }),
+            Predicate::Author(m) => item.map(|i| i.data.authors.iter().any(|a| m.is_match(a))),
+            Predicate::Link(m) => item.map(|i| i.data.links.iter().any(|l| m.is_match(&l.0.href))),
+            Predicate::Posted { from, to } => item.map(|i| {
+                i.data.posted.is_some_and(|posted| {
+                    from.is_none_or(|from| posted >= from) && to.is_none_or(|to| posted < to)
+                })
+            }),
+            Predicate::Text(m) => match (item, feed, tag) {
+                (Some(i), _, _) => Some(
+                    [&i.data.title, &i.data.summary, &i.data.content]
+                        .into_iter()
+                        .flatten()
+                        .any(|t| m.is_match(t)),
+                ),
+                (None, Some(f), _) => m.is_match(&f.name()).then_some(true),
+                (None, None, Some(t)) => Some(m.is_match(&t.name)),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    Text(String),
+    Regex(Regex),
+}
+impl Matcher {
+    fn is_match(&self, s: &str) -> bool {
+        match self {
+            Matcher::Text(t) => s.to_lowercase().contains(t),
+            Matcher::Regex(re) => re.is_match(s),
+        }
+    }
+    fn is_exact(&self, s: &str) -> bool {
+        match self {
+            Matcher::Text(t) => s.to_lowercase() == *t,
+            Matcher::Regex(re) => re.is_match(s),
+        }
+    }
+}
+impl PartialEq for Matcher {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Matcher::Text(a), Matcher::Text(b)) => a == b,
+            (Matcher::Regex(a), Matcher::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub pos: usize,
+    pub msg: String,
+}
+impl ParseError {
+    fn new(pos: usize, msg: String) -> Self {
+        Self { pos, msg }
+    }
+}
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at column {})", self.msg, self.pos + 1)
+    }
+}
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Copy)]
+struct Ctx<'a> {
+    feed: Option<&'a Feed>,
+    item: Option<&'a Item>,
+    tag: Option<&'a Tag>,
+}
+
+#[derive(Debug)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Pred(Predicate),
+}
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::Not => write!(f, "NOT"),
+            Token::Pred(_) => write!(f, "predicate"),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    end: usize,
+}
+impl Parser {
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and()?;
+        while let Some((_, Token::Or)) = self.tokens.get(self.pos) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.unary()?;
+        loop {
+            match self.tokens.get(self.pos) {
+                Some((_, Token::And)) => self.pos += 1,
+                Some((_, Token::Not | Token::LParen | Token::Pred(_))) => {}
+                _ => return Ok(expr),
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if let Some((_, Token::Not)) = self.tokens.get(self.pos) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let Some((pos, token)) = self.tokens.get_mut(self.pos) else {
+            return Err(ParseError::new(self.end, "expected an expression".into()));
+        };
+        let pos = *pos;
+        self.pos += 1;
+        match token {
+            Token::Pred(p) => Ok(Expr::Pred(p.clone())),
+            Token::LParen => {
+                let expr = self.or()?;
+                match self.tokens.get(self.pos) {
+                    Some((_, Token::RParen)) => {
+                        self.pos += 1;
+                        Ok(expr)
+                    }
+                    Some((pos, token)) => Err(ParseError::new(
+                        *pos,
+                        format!("expected ')', found {token}"),
+                    )),
+                    None => Err(ParseError::new(
+                        self.end,
+                        format!("unclosed '(' opened at column {}", pos + 1),
+                    )),
+                }
+            }
+            token => Err(ParseError::new(
+                pos,
+                format!("expected an expression, found {token}"),
+            )),
+        }
+    }
+}
+
+struct Lexer<'a> {
+    query: &'a str,
+    pos: usize,
+}
+impl Lexer<'_> {
+    fn peek(&self) -> Option<char> {
+        self.query[self.pos..].chars().next()
+    }
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+    fn bump_while(&mut self, f: impl Fn(char) -> bool) -> &str {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            let _ = self.bump();
+        }
+        &self.query[start..self.pos]
+    }
+    fn tokens(mut self) -> Result<Vec<(usize, Token)>, ParseError> {
+        let mut tokens = vec![];
+        while let Some(c) = self.peek() {
+            let pos = self.pos;
+            let token = match c {
+                c if c.is_whitespace() => {
+                    let _ = self.bump();
+                    continue;
+                }
+                '(' | ')' => {
+                    let _ = self.bump();
+                    if c == '(' {
+                        Token::LParen
+                    } else {
+                        Token::RParen
+                    }
+                }
+                '!' => {
+                    let _ = self.bump();
+                    Token::Not
+                }
+                '-' if self.query[pos + 1..].starts_with(|c: char| !c.is_whitespace()) => {
+                    let _ = self.bump();
+                    Token::Not
+                }
+                '&' | '|' => {
+                    let _ = self.bump();
+                    if self.bump() != Some(c) {
+                        return Err(ParseError::new(pos, format!("expected '{c}{c}'")));
+                    }
+                    if c == '&' {
+                        Token::And
+                    } else {
+                        Token::Or
+                    }
+                }
+                _ => self.term()?,
+            };
+            tokens.push((pos, token));
+        }
+        Ok(tokens)
+    }
+    fn term(&mut self) -> Result<Token, ParseError> {
+        let start = self.pos;
+        let name = self.bump_while(|c| c.is_ascii_alphabetic()).to_lowercase();
+        // anything else before a colon, like the scheme of a url, is text
+        if FIELDS.contains(&name.as_str()) && self.peek() == Some(':') {
+            let _ = self.bump();
+            let value_pos = self.pos;
+            let value = self.value()?;
+            return field(start, &name, value_pos, value);
+        }
+        self.pos = start;
+        match self.value()? {
+            Value::Word(word) => Ok(match word.as_str() {
+                "AND" => Token::And,
+                "OR" => Token::Or,
+                "NOT" => Token::Not,
+                _ => Token::Pred(
+                    keyword(&word.to_lowercase())
+                        .unwrap_or(Predicate::Text(Matcher::Text(word.to_lowercase()))),
+                ),
+            }),
+            value => Ok(Token::Pred(Predicate::Text(value.into_matcher(start)?))),
+        }
+    }
+    fn value(&mut self) -> Result<Value, ParseError> {
+        let start = self.pos;
+        match self.peek() {
+            Some('"') => {
+                let _ = self.bump();
+                let mut text = String::new();
+                loop {
+                    match self.bump() {
+                        Some('"') => return Ok(Value::Quoted(text)),
+                        Some('\\') if self.peek() == Some('"') => text.push(self.bump().unwrap()),
+                        Some(c) => text.push(c),
+                        None => return Err(ParseError::new(start, "unclosed '\"'".into())),
+                    }
+                }
+            }
+            Some('/') => {
+                let _ = self.bump();
+                let mut pattern = String::new();
+                loop {
+                    match self.bump() {
+                        Some('/') => break,
+                        Some('\\') if self.peek() == Some('/') => {
+                            pattern.push(self.bump().unwrap())
+                        }
+                        Some(c) => pattern.push(c),
+                        None => return Err(ParseError::new(start, "unclosed '/'".into())),
+                    }
+                }
+                let case_insensitive = self.peek() == Some('i');
+                if case_insensitive {
+                    let _ = self.bump();
+                }
+                Ok(Value::Regex(pattern, case_insensitive))
+            }
+            _ => {
+                let word = self.bump_while(|c| !c.is_whitespace() && c != '(' && c != ')');
+                match word.is_empty() {
+                    true => Err(ParseError::new(start, "expected a value".into())),
+                    false => Ok(Value::Word(word.to_string())),
+                }
+            }
+        }
+    }
+}
+
+enum Value {
+    Word(String),
+    Quoted(String),
+    Regex(String, bool),
+}
+impl Value {
+    fn into_matcher(self, pos: usize) -> Result<Matcher, ParseError> {
+        match self {
+            Value::Word(s) | Value::Quoted(s) => Ok(Matcher::Text(s.to_lowercase())),
+            Value::Regex(pattern, case_insensitive) => RegexBuilder::new(&pattern)
+                .case_insensitive(case_insensitive)
+                .build()
+                .map(Matcher::Regex)
+                .map_err(|e| ParseError::new(pos, format!("invalid regex /{pattern}/: {e}"))),
+        }
+    }
+}
+
+fn lex(query: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    Lexer { query, pos: 0 }.tokens()
+}
+
+fn keyword(word: &str) -> Option<Predicate> {
+    match word {
+        "unread" => Some(Predicate::Unread),
+        "read" => Some(Predicate::Read),
+        "starred" => Some(Predicate::Starred),
+        "filtered" => Some(Predicate::Filtered),
+        "unhealthy" => Some(Predicate::Unhealthy),
+        _ => None,
+    }
+}
+
+const FIELDS: &[&str] = &[
+    "is", "tag", "feed", "url", "title", "content", "author", "link", "posted",
+];
+
+fn field(pos: usize, name: &str, value_pos: usize, value: Value) -> Result<Token, ParseError> {
+    let pred = match name {
+        "is" => match &value {
+            Value::Word(word) => keyword(&word.to_lowercase()),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            let msg = "expected one of: unread, read, starred, filtered, unhealthy";
+            ParseError::new(value_pos, msg.into())
+        })?,
+        "tag" => Predicate::Tag(value.into_matcher(value_pos)?),
+        "feed" => Predicate::Feed(value.into_matcher(value_pos)?),
+        "url" => Predicate::Url(value.into_matcher(value_pos)?),
+        "title" => Predicate::Title(value.into_matcher(value_pos)?),
+        "content" => Predicate::Content(value.into_matcher(value_pos)?),
+        "author" => Predicate::Author(value.into_matcher(value_pos)?),
+        "link" => Predicate::Link(value.into_matcher(value_pos)?),
+        "posted" => match value {
+            Value::Word(word) => posted(value_pos, &word)?,
+            _ => return Err(ParseError::new(value_pos, "expected a date".into())),
+        },
+        name => return Err(ParseError::new(pos, format!("unknown field '{name}'"))),
+    };
+    Ok(Token::Pred(pred))
+}
+
+fn posted(pos: usize, value: &str) -> Result<Predicate, ParseError> {
+    let (op, date) = match value.find(|c: char| !"<>=".contains(c)) {
+        Some(i) => value.split_at(i),
+        None => (value, ""),
+    };
+    let Some((start, end)) = date_span(date) else {
+        let msg = format!("invalid date '{date}', expected e.g. 7d, 2w, 2024-01-31");
+        return Err(ParseError::new(pos + op.len(), msg));
+    };
+    let (from, to) = match op {
+        ">" => (Some(end), None),
+        ">=" => (Some(start), None),
+        "<" => (None, Some(start)),
+        "<=" => (None, Some(end)),
+        "" | "=" if start == end => (Some(start), None),
+        "" | "=" => (Some(start), Some(end)),
+        op => return Err(ParseError::new(pos, format!("unknown operator '{op}'"))),
+    };
+    Ok(Predicate::Posted { from, to })
+}
+
+pub fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    date_span(date).map(|(start, _)| start)
+}
+
+fn date_span(date: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    if let Ok(day) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        let start = day.and_hms_opt(0, 0, 0)?.and_utc();
+        return Some((start, start + TimeDelta::days(1)));
+    }
+    if let Ok(t) = DateTime::parse_from_rfc3339(date) {
+        let t = t.with_timezone(&Utc);
+        return Some((t, t));
+    }
+    let unit = date.trim_start_matches(|c: char| c.is_ascii_digit());
+    let n = date[..date.len() - unit.len()].parse::<i64>().ok()?;
+    let delta = match unit {
+        "h" => TimeDelta::try_hours(n),
+        "d" => TimeDelta::try_days(n),
+        "w" => TimeDelta::try_weeks(n),
+        "mo" => TimeDelta::try_days(n.checked_mul(30)?),
+        "y" => TimeDelta::try_days(n.checked_mul(365)?),
+        _ => None,
+    }?;
+    let t = Utc::now().checked_sub_signed(delta)?;
+    Some((t, t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Expr {
+        Expr::Pred(Predicate::Text(Matcher::Text(s.into())))
+    }
+    fn and(a: Expr, b: Expr) -> Expr {
+        Expr::And(Box::new(a), Box::new(b))
+    }
+    fn or(a: Expr, b: Expr) -> Expr {
+        Expr::Or(Box::new(a), Box::new(b))
+    }
+    fn not(e: Expr) -> Expr {
+        Expr::Not(Box::new(e))
+    }
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+    fn posted(from: Option<&str>, to: Option<&str>) -> Expr {
+        Expr::Pred(Predicate::Posted {
+            from: from.map(utc),
+            to: to.map(utc),
+        })
+    }
+    fn item(posted: &str) -> Item {
+        serde_json::from_value(serde_json::json!({
+            "data": {
+                "id": ["https://example.com/feed", "1"],
+                "title": "Hello World",
+                "content": null,
+                "summary": null,
+                "authors": [],
+                "categories": [],
+                "media": [],
+                "posted": posted,
+                "links": [],
+            },
+            "state": {"read_on": null, "is_filtered": false},
+        }))
+        .unwrap()
+    }
+    fn error(query: &str) -> (usize, String) {
+        let e = Expr::parse(query).unwrap_err();
+        (e.pos, e.msg)
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let parse = |q| Expr::parse(q).unwrap();
+        assert_eq!(parse("a b OR c"), or(and(text("a"), text("b")), text("c")));
+        assert_eq!(
+            parse("a || b && c"),
+            or(text("a"), and(text("b"), text("c")))
+        );
+        assert_eq!(parse("NOT a AND b"), and(not(text("a")), text("b")));
+        assert_eq!(
+            parse("-a (b OR c)"),
+            and(not(text("a")), or(text("b"), text("c")))
+        );
+        assert_eq!(parse("!!a"), not(not(text("a"))));
+        assert_eq!(parse("a - b"), and(and(text("a"), text("-")), text("b")));
+    }
+
+    #[test]
+    fn quoted_values_are_literal() {
+        let parse = |q| Expr::parse(q).unwrap();
+        assert_eq!(parse(r#""AND""#), text("and"));
+        assert_eq!(parse("and or"), and(text("and"), text("or")));
+        assert_eq!(parse(r#""Hello World""#), text("hello world"));
+        assert_eq!(parse(r#""say \"hi\"""#), text(r#"say "hi""#));
+        assert_eq!(
+            parse(r#"title:"a (b) OR c""#),
+            Expr::Pred(Predicate::Title(Matcher::Text("a (b) or c".into())))
+        );
+        assert_eq!(parse("is:Unread"), Expr::Pred(Predicate::Unread));
+
+        let item = item("2024-01-01T00:00:00Z");
+        assert!(Filter::parse(r#""hello world""#).unwrap().test(&item));
+        assert!(!Filter::parse(r#""world hello""#).unwrap().test(&item));
+        assert!(Filter::parse(r"title:/^hello\/?/i").unwrap().test(&item));
+        assert!(!Filter::parse(r"title:/^hello/").unwrap().test(&item));
+    }
+
+    #[test]
+    fn dates_cover_their_whole_span() {
+        let parse = |q| Expr::parse(q).unwrap();
+        let (day, next) = ("2024-01-31T00:00:00Z", "2024-02-01T00:00:00Z");
+        assert_eq!(parse("posted:2024-01-31"), posted(Some(day), Some(next)));
+        assert_eq!(parse("posted:=2024-01-31"), posted(Some(day), Some(next)));
+        assert_eq!(parse("posted:>2024-01-31"), posted(Some(next), None));
+        assert_eq!(parse("posted:>=2024-01-31"), posted(Some(day), None));
+        assert_eq!(parse("posted:<2024-01-31"), posted(None, Some(day)));
+        assert_eq!(parse("posted:<=2024-01-31"), posted(None, Some(next)));
+        assert_eq!(
+            parse("posted:2024-01-31T12:00:00Z"),
+            posted(Some("2024-01-31T12:00:00Z"), None)
+        );
+
+        let filter = Filter::parse("posted:2024-01-31").unwrap();
+        assert!(filter.test(&item("2024-01-31T00:00:00Z")));
+        assert!(filter.test(&item("2024-01-31T23:59:59Z")));
+        assert!(!filter.test(&item("2024-02-01T00:00:00Z")));
+        assert!(!filter.test(&item("2024-01-30T23:59:59Z")));
+
+        let Expr::Pred(Predicate::Posted {
+            from: Some(from),
+            to: None,
+        }) = parse("posted:7d")
+        else {
+            panic!("relative dates are a lower bound");
+        };
+        let age = Utc::now() - from;
+        assert!(age >= TimeDelta::days(7) && age < TimeDelta::days(7) + TimeDelta::minutes(1));
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_column() {
+        assert_eq!(error("a AND").0, 5);
+        assert_eq!(
+            error("(a OR b"),
+            (7, "unclosed '(' opened at column 1".into())
+        );
+        assert_eq!(error("a )"), (2, "unexpected ')'".into()));
+        assert_eq!(
+            error("a (b c"),
+            (6, "unclosed '(' opened at column 3".into())
+        );
+        assert_eq!(
+            error("(OR a)"),
+            (1, "expected an expression, found OR".into())
+        );
+        assert_eq!(error("a & b"), (2, "expected '&&'".into()));
+        assert_eq!(error(r#"x "abc"#), (2, "unclosed '\"'".into()));
+        assert_eq!(error("is:nothing").0, 3);
+        assert_eq!(error("title:").0, 6);
+        assert_eq!(error("a title:/(/").0, 8);
+        assert_eq!(error("posted:>bogus").0, 8);
+        assert_eq!(error("posted:\"7d\""), (7, "expected a date".into()));
+        assert_eq!(error("posted:<>7d"), (7, "unknown operator '<>'".into()));
+
+        let e = Expr::parse("(a OR b").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "unclosed '(' opened at column 1 (at column 8)"
+        );
+    }
+
+    #[test]
+    fn hidden_items_are_only_left_out_of_listings() {
+        let mut item = item("2024-01-01T00:00:00Z");
+        item.state.effects.hidden = true;
+        let filter = Filter::new().unread_item();
+        assert!(filter.test(&item));
+        assert!(!filter.clone().visible().test(&item));
+        item.state.effects.hidden = false;
+        assert!(filter.visible().test(&item));
+    }
+
+    #[test]
+    fn unknown_prefixes_are_text() {
+        let parse = |q| Expr::parse(q).unwrap();
+        assert_eq!(
+            parse("https://example.com/x"),
+            text("https://example.com/x")
+        );
+        assert_eq!(parse("foo:bar"), text("foo:bar"));
+        assert_eq!(
+            parse("Title:https://example.com"),
+            Expr::Pred(Predicate::Title(Matcher::Text(
+                "https://example.com".into()
+            )))
+        );
+
+        let mut item = item("2024-01-01T00:00:00Z");
+        item.data.content = Some("see https://example.com/x for more".into());
+        assert!(Filter::parse("https://example.com/x").unwrap().test(&item));
+    }
+}
@@ -1,13 +1,39 @@
+use std::sync::{Arc, RwLock};
+
 use lazy_static::lazy_static;
-use ratatui_helpers::config::parse_toml;
 
-use crate::config::{Config, PartialConfig};
+use crate::config::Config;
+use crate::error::ConfigError;
 
 pub const PROJECT_NAME: &str = "rrss";
-pub const CACHE_FILE: &str = "feeds.bin";
+pub const CACHE_FILE: &str = "feeds.db";
+pub const LEGACY_CACHE_FILE: &str = "feeds.bin";
 pub const CONFIG_FILE: &str = "config.toml";
 pub const SOURCES_FILE: &str = "sources.toml";
+pub const PID_FILE: &str = "daemon.pid";
+pub const LOG_FILE: &str = "daemon.log";
 
 lazy_static! {
-    pub static ref CONFIG: Config = parse_toml::<PartialConfig, _>(PROJECT_NAME, CONFIG_FILE);
+    pub static ref CONFIG: LiveConfig = LiveConfig::new(Config::load().0);
+}
+
+// readers take a snapshot of the config, so a reload only affects what
+// starts after it
+pub struct LiveConfig(RwLock<Arc<Config>>);
+impl LiveConfig {
+    fn new(config: Config) -> Self {
+        Self(RwLock::new(Arc::new(config)))
+    }
+    pub fn get(&self) -> Arc<Config> {
+        self.0.read().unwrap().clone()
+    }
+    fn set(&self, config: Config) {
+        *self.0.write().unwrap() = Arc::new(config);
+    }
+}
+
+pub fn reload_config() -> Vec<ConfigError> {
+    let (config, errors) = Config::load();
+    CONFIG.set(config);
+    errors
 }
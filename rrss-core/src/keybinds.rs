@@ -0,0 +1,333 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+// keys pressed one after the other, like "g g"
+pub type KeySequence = Vec<KeyEvent>;
+
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum PartialKeys {
+    One(String),
+    Many(Vec<String>),
+}
+impl PartialKeys {
+    pub fn parse(&self) -> Result<Vec<KeySequence>, String> {
+        match self {
+            PartialKeys::One(keys) => Ok(vec![parse_keys(keys)?]),
+            PartialKeys::Many(keys) => keys.iter().map(|k| parse_keys(k)).collect(),
+        }
+    }
+}
+
+// the commands each `[keybinds.<view>]` section accepts, so that the config
+// can be checked without the tui. the tui tests its keymaps against it
+pub const VIEW_COMMANDS: &[(&str, &[&str])] = &[
+    (
+        "global",
+        &[
+            "quit_view",
+            "help",
+            "search",
+            "global_search",
+            "undo",
+            "redo",
+            "reload",
+        ],
+    ),
+    (
+        "feeds",
+        &[
+            "update_feeds",
+            "update_feed",
+            "clear_filters",
+            "mark_feed_as_read",
+            "view_feed_links",
+            "view_feed_info",
+            "view_tags",
+            "open_feed",
+            "clear_feed",
+            "view_health",
+            "add_feed",
+            "remove_feed",
+            "tag_feed",
+            "view_timeline",
+            "view_starred",
+            "edit_max_items",
+        ],
+    ),
+    (
+        "items",
+        &[
+            "open_item",
+            "view_item",
+            "update_feed",
+            "mark_item_as_read",
+            "toggle_read",
+            "toggle_starred",
+            "view_item_info",
+            "clear_filters",
+            "view_item_links",
+        ],
+    ),
+    (
+        "item",
+        &[
+            "open_item",
+            "open_links",
+            "open_item_info",
+            "toggle_read",
+            "toggle_starred",
+            "next_item",
+            "prev_item",
+        ],
+    ),
+    (
+        "scroll",
+        &["go_down", "go_up", "go_page_down", "go_page_up"],
+    ),
+    (
+        "tags",
+        &[
+            "update_tag",
+            "mark_tag_as_read",
+            "view_timeline",
+            "open_tag",
+        ],
+    ),
+    ("links", &["open_link"]),
+    ("errors", &["update_feed", "view_feed_info"]),
+    ("health", &["update_feed", "view_feed_info", "open_feed"]),
+    ("discovery", &["add_feed"]),
+];
+
+#[derive(Debug, Clone)]
+pub struct Keybind {
+    pub view: String,
+    pub command: String,
+    pub keys: Vec<KeySequence>,
+}
+
+pub fn check_command(view: &str, command: &str) -> Result<(), String> {
+    match VIEW_COMMANDS.iter().find(|(v, _)| *v == view) {
+        None => Err(format!("unknown view '{view}'")),
+        Some((_, commands)) if !commands.contains(&command) => {
+            Err(format!("unknown command '{command}'"))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+pub fn parse_keys(s: &str) -> Result<KeySequence, String> {
+    let keys = s
+        .split_whitespace()
+        .map(parse_key)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.is_empty() {
+        return Err("empty key".into());
+    }
+    Ok(keys)
+}
+
+fn parse_key(s: &str) -> Result<KeyEvent, String> {
+    let (mods, key) = match s.strip_suffix("--") {
+        Some(mods) => (mods, "-"),
+        None => match s.rsplit_once('-') {
+            Some((mods, key)) if !mods.is_empty() => (mods, key),
+            _ => ("", s),
+        },
+    };
+    let mut modifiers = KeyModifiers::NONE;
+    for m in mods.split('-').filter(|m| !m.is_empty()) {
+        modifiers |= match m.to_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier '{m}' in '{s}'")),
+        };
+    }
+    let mut chars = key.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match key.to_lowercase().as_str() {
+            "enter" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                _ => return Err(format!("unknown key '{key}' in '{s}'")),
+            },
+        },
+    };
+    let (code, modifiers) = normalize(code, modifiers);
+    Ok(KeyEvent::new(code, modifiers))
+}
+
+// 'J' and shift-j are the same key, whichever way the terminal reports it.
+// for other chars like '?' shift is already part of the char, and only some
+// terminals report it
+pub fn normalize(code: KeyCode, modifiers: KeyModifiers) -> (KeyCode, KeyModifiers) {
+    match code {
+        KeyCode::Char(c) if c.is_uppercase() => (
+            KeyCode::Char(c.to_lowercase().next().unwrap_or(c)),
+            modifiers | KeyModifiers::SHIFT,
+        ),
+        KeyCode::Char(c) if !c.is_alphabetic() => (code, modifiers - KeyModifiers::SHIFT),
+        code => (code, modifiers),
+    }
+}
+
+pub fn key_to_string(key: &KeyEvent) -> String {
+    let (code, mut modifiers) = normalize(key.code, key.modifiers);
+    let name = match code {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) && c.is_alphabetic() => {
+            modifiers.remove(KeyModifiers::SHIFT);
+            c.to_uppercase().to_string()
+        }
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("f{n}"),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        KeyCode::BackTab => "backtab".to_string(),
+        code => format!("{code:?}").to_lowercase(),
+    };
+    let mut s = String::new();
+    for (m, prefix) in [
+        (KeyModifiers::CONTROL, "ctrl-"),
+        (KeyModifiers::ALT, "alt-"),
+        (KeyModifiers::SHIFT, "shift-"),
+    ] {
+        if modifiers.contains(m) {
+            s.push_str(prefix);
+        }
+    }
+    s + &name
+}
+
+pub fn keys_to_string(keys: &KeySequence) -> String {
+    keys.iter().map(key_to_string).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn keys_and_modifiers() {
+        let none = KeyModifiers::NONE;
+        assert_eq!(parse_keys("j"), Ok(vec![key(KeyCode::Char('j'), none)]));
+        assert_eq!(
+            parse_keys("ctrl-alt-Delete"),
+            Ok(vec![key(
+                KeyCode::Delete,
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )])
+        );
+        assert_eq!(parse_keys("-"), Ok(vec![key(KeyCode::Char('-'), none)]));
+        assert_eq!(
+            parse_keys("ctrl--"),
+            Ok(vec![key(KeyCode::Char('-'), KeyModifiers::CONTROL)])
+        );
+        assert_eq!(parse_keys("space"), Ok(vec![key(KeyCode::Char(' '), none)]));
+        assert_eq!(parse_keys("F12"), Ok(vec![key(KeyCode::F(12), none)]));
+        assert_eq!(
+            parse_keys(" g  g "),
+            Ok(vec![key(KeyCode::Char('g'), none); 2])
+        );
+    }
+
+    #[test]
+    fn uppercase_is_shift() {
+        let shift_j = vec![key(KeyCode::Char('j'), KeyModifiers::SHIFT)];
+        assert_eq!(parse_keys("J"), Ok(shift_j.clone()));
+        assert_eq!(parse_keys("shift-j"), Ok(shift_j.clone()));
+        assert_eq!(parse_keys("Shift-J"), Ok(shift_j.clone()));
+        assert_eq!(keys_to_string(&shift_j), "J");
+    }
+
+    #[test]
+    fn shift_is_dropped_from_symbols() {
+        let question = key(KeyCode::Char('?'), KeyModifiers::NONE);
+        let (code, modifiers) = normalize(KeyCode::Char('?'), KeyModifiers::SHIFT);
+        assert_eq!(key(code, modifiers), question);
+        assert_eq!(parse_keys("shift-?"), Ok(vec![question]));
+        assert_eq!(
+            parse_keys("ctrl-shift-1"),
+            Ok(vec![key(KeyCode::Char('1'), KeyModifiers::CONTROL)])
+        );
+        assert_eq!(
+            keys_to_string(&vec![key(KeyCode::Char('?'), KeyModifiers::SHIFT)]),
+            "?"
+        );
+        // keys other than chars keep it
+        assert_eq!(
+            normalize(KeyCode::Tab, KeyModifiers::SHIFT),
+            (KeyCode::Tab, KeyModifiers::SHIFT)
+        );
+    }
+
+    #[test]
+    fn commands_are_checked_per_view() {
+        assert_eq!(check_command("feeds", "update_feeds"), Ok(()));
+        assert_eq!(
+            check_command("feeds", "updte_feeds"),
+            Err("unknown command 'updte_feeds'".into())
+        );
+        assert_eq!(
+            check_command("tags", "update_feeds"),
+            Err("unknown command 'update_feeds'".into())
+        );
+        assert_eq!(
+            check_command("feed", "update_feeds"),
+            Err("unknown view 'feed'".into())
+        );
+    }
+
+    #[test]
+    fn invalid_keys() {
+        assert_eq!(parse_keys("  "), Err("empty key".into()));
+        assert_eq!(
+            parse_keys("g meta-x"),
+            Err("unknown modifier 'meta' in 'meta-x'".into())
+        );
+        assert_eq!(parse_keys("f13"), Err("unknown key 'f13' in 'f13'".into()));
+        assert_eq!(
+            parse_keys("ctrl-foo"),
+            Err("unknown key 'foo' in 'ctrl-foo'".into())
+        );
+    }
+
+    #[test]
+    fn strings_parse_back() {
+        for s in [
+            "g g",
+            "ctrl-r",
+            "J",
+            "ctrl-shift-tab",
+            "alt-left",
+            "space",
+            "f5",
+            "pagedown",
+        ] {
+            let keys = parse_keys(s).unwrap();
+            assert_eq!(parse_keys(&keys_to_string(&keys)), Ok(keys), "{s}");
+        }
+    }
+}
@@ -1,7 +1,14 @@
 pub mod cache;
 pub mod config;
+pub mod diagnostics;
+pub mod discovery;
+pub mod error;
 pub mod feed_manager;
 pub mod filter;
 pub mod globals;
+pub mod keybinds;
 pub mod models;
+pub mod opml;
+pub mod scheduler;
+pub mod search;
 pub mod sorter;
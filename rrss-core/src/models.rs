@@ -1,18 +1,21 @@
 use std::cmp::Ordering;
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
+use std::time::Duration;
 
 use chrono::{DateTime, Utc};
 use chrono_humanize::{Accuracy, HumanTime, Tense};
 use itertools::Itertools;
 use notify_rust::Notification;
 use ratatui::layout::{Alignment, Constraint};
-use ratatui::style::Style;
+use ratatui::style::{Color, Style};
 use ratatui_helpers::stateful_table::Tabular;
-use regex::RegexBuilder;
 use serde::{Deserialize, Serialize};
+use url::Url;
 
-use crate::config::{FeedFilter, FeedSource};
+use crate::config::{FeedFilter, FeedSource, Rule, RuleAction, RuleField};
+use crate::error::FetchError;
+use crate::feed_manager::FetchRequest;
 use crate::globals::CONFIG;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
@@ -25,48 +28,116 @@ pub struct Feed {
     pub data: Option<FeedData>,
 }
 impl Feed {
-    pub fn refresh_items_state(&mut self) {
+    pub fn new(conf: FeedSource) -> Self {
+        Self {
+            conf,
+            state: FeedState::default(),
+            data: None,
+        }
+    }
+    // rule effects are recomputed for every item, but state changes like
+    // `read` only apply to the `fresh` items, so they can be undone
+    pub fn refresh_items_state(&mut self, fresh: &[ItemId]) {
+        let config = CONFIG.get();
+        let rules = self.conf.rules.iter().chain(&config.rules).collect_vec();
         if let Some(data) = &mut self.data {
             data.items.iter_mut().for_each(|i| {
                 if let Some(filter) = &self.conf.filter {
                     i.state.is_filtered = filter.invert ^ i.title_matches(filter);
                 }
+                let is_fresh = fresh.contains(&i.data.id);
+                i.apply_rules(&rules, is_fresh);
             });
         }
     }
     pub fn merge_feed(&mut self, mut new: FeedData) {
         let name = &self.name();
+        let fresh: Vec<ItemId>;
         match &mut self.data {
             Some(old) => {
-                let old_items = std::mem::take(&mut old.items);
-                new.items.retain(|i| !old_items.contains(i));
+                let mut old_items = std::mem::take(&mut old.items);
+                // entries can be edited after they are published, so refetched
+                // ones replace the stored data but keep their state
+                new.items
+                    .retain(|i| match old_items.iter_mut().find(|o| *o == i) {
+                        Some(old) => {
+                            old.data = i.data.clone();
+                            false
+                        }
+                        None => true,
+                    });
                 new.items.sort_by(Item::BY_POSTED_REV.0);
-                new.items.truncate(self.conf.max_items as usize);
+                truncate_items(&mut new.items, self.conf.max_items as usize);
 
                 if self.conf.notify && !new.items.is_empty() {
                     notify_new_items(name, &new.items);
                 }
 
+                fresh = new.items.iter().map(|i| i.data.id.clone()).collect();
                 new.items.extend(old_items);
                 new.items.sort_by(Item::BY_POSTED_REV.0);
-                new.items.truncate(self.conf.max_items as usize);
+                truncate_items(&mut new.items, self.conf.max_items as usize);
                 old.items = new.items;
             }
             _ => {
                 new.items.sort_by(Item::BY_POSTED_REV.0);
-                new.items.truncate(self.conf.max_items as usize);
+                truncate_items(&mut new.items, self.conf.max_items as usize);
                 if self.conf.notify && !new.items.is_empty() {
                     notify_new_items(name, &new.items);
                 }
+                fresh = new.items.iter().map(|i| i.data.id.clone()).collect();
                 self.data = Some(new)
             }
         };
-        self.refresh_items_state();
+        self.refresh_items_state(&fresh);
         self.refresh_feed_state();
+
+        if !self.conf.notify {
+            let notify = self
+                .items()
+                .into_iter()
+                .flatten()
+                .filter(|i| i.state.effects.notify && fresh.contains(&i.data.id))
+                .cloned()
+                .collect_vec();
+            if !notify.is_empty() {
+                notify_new_items(name, &notify);
+            }
+        }
     }
     pub fn update_bytes(&mut self, bytes: usize) {
         self.state.exchanged_bytes += bytes;
     }
+    pub fn update_validators(&mut self, etag: Option<String>, last_modified: Option<String>) {
+        self.state.etag = etag;
+        self.state.last_modified = last_modified;
+    }
+    pub fn record_success(&mut self, status: u16) {
+        self.state.last_success = Some(Utc::now());
+        self.state.last_status = Some(status);
+        self.state.last_error = None;
+        self.state.failure_streak = 0;
+    }
+    pub fn record_failure(&mut self, e: &FetchError) {
+        self.state.last_failure = Some(Utc::now());
+        self.state.last_status = e.status();
+        self.state.last_error = Some(format!("{}: {}", e.kind(), e.reason()));
+        self.state.failure_streak += 1;
+    }
+    pub fn is_healthy(&self) -> bool {
+        self.state.failure_streak == 0
+    }
+    pub fn fetch_request(&self) -> FetchRequest {
+        FetchRequest {
+            id: self.id().clone(),
+            etag: self.state.etag.clone(),
+            last_modified: self.state.last_modified.clone(),
+            timeout: self
+                .conf
+                .timeout
+                .map_or(CONFIG.get().fetch_timeout, Duration::from_secs),
+        }
+    }
     pub fn url(&self) -> String {
         self.conf.url.0.to_string()
     }
@@ -84,8 +155,20 @@ impl Feed {
     pub fn items_mut(&mut self) -> Option<&mut Vec<Item>> {
         self.data.as_mut().map(|d| &mut d.items)
     }
+    pub fn truncate_items(&mut self) {
+        let max_items = self.conf.max_items as usize;
+        if let Some(items) = self.items_mut() {
+            truncate_items(items, max_items);
+        }
+    }
     pub fn clear_data(&mut self) {
-        self.data = None;
+        if let Some(data) = &mut self.data {
+            data.items.retain(|i| i.state.starred);
+        }
+        if self.items().is_none_or(|i| i.is_empty()) {
+            self.data = None;
+        }
+        self.update_validators(None, None);
     }
     pub fn increment_hits(&mut self) {
         self.state.hits += 1;
@@ -94,7 +177,7 @@ impl Feed {
         if let Some(feed) = &self.data {
             self.state.latest_item_date = feed.items.iter().map(|i| i.data.posted).max().flatten();
             self.state.is_recent = self.state.latest_item_date.is_some_and(|date| {
-                (Utc::now() - date).num_days() < CONFIG.relative_time_threshold as i64
+                (Utc::now() - date).num_days() < CONFIG.get().relative_time_threshold as i64
             })
         }
     }
@@ -103,13 +186,18 @@ impl Feed {
             return false;
         }
         self.items().is_some_and(|i| {
-            i.iter()
-                .any(|i| i.state.read_on.is_none() && !i.state.is_filtered)
+            i.iter().any(|i| {
+                i.state.read_on.is_none() && !i.state.is_filtered && !i.state.effects.hidden
+            })
         })
     }
     pub fn tot_unread(&self) -> usize {
         self.items()
-            .map(|i| i.iter().filter(|i| i.state.read_on.is_none()).count())
+            .map(|i| {
+                i.iter()
+                    .filter(|i| i.state.read_on.is_none() && !i.state.effects.hidden)
+                    .count()
+            })
             .unwrap_or_default()
     }
     pub fn name(&self) -> String {
@@ -151,8 +239,8 @@ impl Tabular for Feed {
 
         let marker = match () {
             _ if self.has_new_unfiltered() => '*', // todo: add config option
-            _ if tot_unread > 0 => CONFIG.theme.unread_marker,
-            _ => CONFIG.theme.read_marker,
+            _ if tot_unread > 0 => CONFIG.get().theme.unread_marker,
+            _ => CONFIG.get().theme.read_marker,
         };
 
         let latest_item_date = self
@@ -196,7 +284,7 @@ impl Tabular for Feed {
             style = style.fg(ratatui::style::Color::LightCyan); // todo: add config option
         }
         if self.tot_unread() > 0 {
-            style = style.fg(CONFIG.theme.fg_unread_color);
+            style = style.fg(CONFIG.get().theme.fg_unread_color);
         }
         if self.state.is_recent {
             style = style.fg(ratatui::style::Color::LightGreen);
@@ -215,18 +303,200 @@ impl Tabular for Feed {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct VirtualFeed {
+    pub name: String,
+    pub tot_unread: usize,
+    pub tot_items: usize,
+    pub latest_item_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedRef {
+    Virtual(String),
+    Feed(FeedId),
+}
+
+#[derive(Debug, Clone)]
+pub enum FeedRow {
+    Virtual(VirtualFeed),
+    Feed(Box<Feed>),
+}
+impl Tabular for FeedRow {
+    type Value = FeedRef;
+    fn cmp_by_col(&self, other: &Self, col: usize) -> Ordering {
+        match (self, other) {
+            (FeedRow::Feed(a), FeedRow::Feed(b)) => a.cmp_by_col(b, col),
+            (FeedRow::Virtual(a), FeedRow::Virtual(b)) => match col {
+                2 => a.tot_unread.cmp(&b.tot_unread),
+                3 => a.name.cmp(&b.name),
+                4 => a.latest_item_date.cmp(&b.latest_item_date),
+                _ => Ordering::Equal,
+            },
+            (FeedRow::Virtual(_), FeedRow::Feed(_)) => Ordering::Less,
+            (FeedRow::Feed(_), FeedRow::Virtual(_)) => Ordering::Greater,
+        }
+    }
+    fn value(&self) -> Self::Value {
+        match self {
+            FeedRow::Virtual(v) => FeedRef::Virtual(v.name.clone()),
+            FeedRow::Feed(f) => FeedRef::Feed(f.id().clone()),
+        }
+    }
+    fn content(&self) -> Vec<String> {
+        let v = match self {
+            FeedRow::Virtual(v) => v,
+            FeedRow::Feed(f) => return f.content(),
+        };
+        let marker = match v.tot_unread {
+            0 => CONFIG.get().theme.read_marker,
+            _ => CONFIG.get().theme.unread_marker,
+        };
+        vec![
+            format!("{}", marker),
+            format!("Search"),
+            format!("({}/{})", v.tot_unread, v.tot_items),
+            format!("{}", v.name),
+            format!(
+                "{}",
+                v.latest_item_date.map(pretty_date).unwrap_or_default()
+            ),
+            format!(""),
+        ]
+    }
+    fn column_names() -> Option<Vec<String>> {
+        Feed::column_names()
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        Feed::column_constraints()
+    }
+    fn style(&self) -> Style {
+        match self {
+            FeedRow::Virtual(v) if v.tot_unread > 0 => {
+                Style::default().fg(CONFIG.get().theme.fg_unread_color)
+            }
+            FeedRow::Virtual(_) => Style::default(),
+            FeedRow::Feed(f) => f.style(),
+        }
+    }
+    fn column_alignments() -> Option<Vec<Alignment>> {
+        Feed::column_alignments()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct FeedState {
     pub latest_item_date: Option<DateTime<Utc>>,
     pub hits: usize,
     pub is_recent: bool,
     pub exchanged_bytes: usize,
+    pub saved_bytes: usize,
+    pub last_size: usize,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_failure: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_status: Option<u16>,
+    pub failure_streak: u32,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Clone)]
+pub struct FeedHealth {
+    pub id: FeedId,
+    pub name: String,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_status: Option<u16>,
+    pub failure_streak: u32,
+}
+impl From<&Feed> for FeedHealth {
+    fn from(feed: &Feed) -> Self {
+        Self {
+            id: feed.id().clone(),
+            name: feed.name(),
+            last_success: feed.state.last_success,
+            last_error: feed.state.last_error.clone(),
+            last_status: feed.state.last_status,
+            failure_streak: feed.state.failure_streak,
+        }
+    }
+}
+impl Tabular for FeedHealth {
+    type Value = FeedId;
+    fn cmp_by_col(&self, other: &Self, col: usize) -> Ordering {
+        match col {
+            0 => self.failure_streak.cmp(&other.failure_streak),
+            1 => self.name.cmp(&other.name),
+            2 => self.last_success.cmp(&other.last_success),
+            3 => self.last_status.cmp(&other.last_status),
+            4 => self.last_error.cmp(&other.last_error),
+            _ => panic!(),
+        }
+    }
+    fn value(&self) -> Self::Value {
+        self.id.clone()
+    }
+    fn content(&self) -> Vec<String> {
+        vec![
+            format!("{}", self.failure_streak),
+            format!("{}", self.name),
+            format!(
+                "{}",
+                self.last_success
+                    .map(pretty_date)
+                    .unwrap_or_else(|| "never".into())
+            ),
+            format!(
+                "{}",
+                self.last_status.map(|s| s.to_string()).unwrap_or_default()
+            ),
+            format!("{}", self.last_error.clone().unwrap_or_default()),
+        ]
+    }
+    fn column_names() -> Option<Vec<String>> {
+        Some(vec![
+            format!("Fails"),
+            format!("Title"),
+            format!("Last success"),
+            format!("Status"),
+            format!("Last error"),
+        ])
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![
+            Constraint::Length,
+            Constraint::Length,
+            Constraint::Min,
+            Constraint::Length,
+            Constraint::Fill,
+        ]
+    }
+    fn style(&self) -> Style {
+        let mut style = Style::default();
+        if self.failure_streak >= CONFIG.get().failure_threshold {
+            style = style.fg(ratatui::style::Color::LightRed); // todo: add config option
+        }
+        style
+    }
+    fn column_alignments() -> Option<Vec<Alignment>> {
+        Some(vec![
+            Alignment::Right,
+            Alignment::Left,
+            Alignment::Left,
+            Alignment::Left,
+            Alignment::Left,
+        ])
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct FeedData {
     pub feed_type: FeedType,
     pub title: String,
+    #[serde(skip)]
     pub items: Vec<Item>,
     pub published: Option<DateTime<Utc>>,
     pub updated: Option<DateTime<Utc>>,
@@ -235,6 +505,7 @@ pub struct FeedData {
     pub description: Option<String>,
     pub categories: Vec<String>,
     pub language: Option<String>,
+    pub ttl: Option<u32>,
 }
 impl FeedData {
     pub fn from(feed: feed_rs::model::Feed, url: &str) -> Self {
@@ -249,6 +520,8 @@ impl FeedData {
                     state: ItemState {
                         read_on: None,
                         is_filtered: false,
+                        starred: false,
+                        effects: RuleEffects::default(),
                     },
                 })
                 .collect_vec(),
@@ -259,6 +532,7 @@ impl FeedData {
             description: feed.description.map(|d| d.content),
             categories: feed.categories.into_iter().map(|c| c.term).collect(),
             language: feed.language,
+            ttl: feed.ttl,
         }
     }
 }
@@ -274,14 +548,63 @@ pub struct Item {
 impl Item {
     pub fn title_matches(&self, filter: &FeedFilter) -> bool {
         if let Some(title) = &self.data.title {
-            return RegexBuilder::new(&filter.pattern)
-                .case_insensitive(filter.case_insensitive)
-                .build()
-                .unwrap()
-                .is_match(title);
+            return filter.is_match(title);
         }
         false
     }
+    pub fn matches_rule(&self, rule: &Rule) -> bool {
+        let data = &self.data;
+        let is_match = match rule.field {
+            RuleField::Title => data.title.iter().any(|t| rule.pattern.is_match(t)),
+            RuleField::Content => [&data.summary, &data.content]
+                .into_iter()
+                .flatten()
+                .any(|c| rule.pattern.is_match(c)),
+            RuleField::Author => data.authors.iter().any(|a| rule.pattern.is_match(a)),
+            RuleField::Category => data.categories.iter().any(|c| rule.pattern.is_match(c)),
+            RuleField::Domain => data
+                .links
+                .iter()
+                .filter_map(|l| Url::parse(&l.0.href).ok())
+                .any(|url| url.host_str().is_some_and(|h| rule.pattern.is_match(h))),
+        };
+        rule.invert ^ is_match
+    }
+    pub fn apply_rules(&mut self, rules: &[&Rule], is_fresh: bool) {
+        let mut effects = RuleEffects::default();
+        let fired = rules.iter().filter(|r| self.matches_rule(r)).collect_vec();
+        for rule in fired {
+            effects.fired.push(rule.name.clone());
+            for action in &rule.actions {
+                match action {
+                    RuleAction::Read => {
+                        if is_fresh && self.state.read_on.is_none() {
+                            self.state.read_on = Some(Utc::now());
+                        }
+                    }
+                    RuleAction::Hide => effects.hidden = true,
+                    RuleAction::Highlight(color) => {
+                        effects.highlight =
+                            Some(color.unwrap_or(CONFIG.get().theme.fg_highlight_color))
+                    }
+                    RuleAction::Tag(tag) => {
+                        if !effects.tags.contains(tag) {
+                            effects.tags.push(tag.clone());
+                        }
+                    }
+                    RuleAction::Notify => effects.notify = true,
+                }
+            }
+        }
+        self.state.effects = effects;
+    }
+    pub fn marker(&self) -> char {
+        match self.state.read_on {
+            _ if self.state.starred => CONFIG.get().theme.starred_marker,
+            None => CONFIG.get().theme.unread_marker,
+            _ => CONFIG.get().theme.read_marker,
+        }
+    }
 }
 impl PartialEq for Item {
     fn eq(&self, other: &Self) -> bool {
@@ -309,12 +632,8 @@ impl Tabular for Item {
         self.data.id.clone()
     }
     fn content(&self) -> Vec<String> {
-        let marker = match self.state.read_on {
-            None => CONFIG.theme.unread_marker,
-            _ => CONFIG.theme.read_marker,
-        };
         vec![
-            format!("{}", marker),
+            format!("{}", self.marker()),
             format!("{}", self.data.title.clone().unwrap_or_default()),
             format!("{}", self.data.posted.map(pretty_date).unwrap_or_default()),
         ]
@@ -329,22 +648,83 @@ impl Tabular for Item {
         let mut style = Style::default();
         if self.state.read_on.is_none() {
             style = style
-                .fg(CONFIG.theme.fg_unread_color)
-                .bg(CONFIG.theme.bg_unread_color);
+                .fg(CONFIG.get().theme.fg_unread_color)
+                .bg(CONFIG.get().theme.bg_unread_color);
         }
         if self.state.is_filtered {
             style = style
-                .fg(CONFIG.theme.fg_filtered_color)
-                .bg(CONFIG.theme.bg_filterd_color);
+                .fg(CONFIG.get().theme.fg_filtered_color)
+                .bg(CONFIG.get().theme.bg_filterd_color);
+        }
+        if let Some(color) = self.state.effects.highlight {
+            style = style.fg(color);
         }
         style
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct TimelineItem {
+    pub item: Item,
+    pub feed: String,
+}
+impl Tabular for TimelineItem {
+    type Value = ItemId;
+    fn cmp_by_col(&self, other: &Self, col: usize) -> Ordering {
+        match col {
+            0 => self.item.cmp_by_col(&other.item, 0),
+            1 => self.feed.cmp(&other.feed),
+            2 => self.item.cmp_by_col(&other.item, 1),
+            3 => self.item.cmp_by_col(&other.item, 2),
+            _ => panic!(),
+        }
+    }
+    fn value(&self) -> Self::Value {
+        self.item.value()
+    }
+    fn content(&self) -> Vec<String> {
+        let mut content = self.item.content();
+        content.insert(1, self.feed.clone());
+        content
+    }
+    fn column_names() -> Option<Vec<String>> {
+        Some(vec![
+            format!("N"),
+            format!("Feed"),
+            format!("Title"),
+            format!("Posted"),
+        ])
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![
+            Constraint::Length,
+            Constraint::Length,
+            Constraint::Fill,
+            Constraint::Min,
+        ]
+    }
+    fn style(&self) -> Style {
+        self.item.style()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ItemState {
     pub read_on: Option<DateTime<Utc>>,
     pub is_filtered: bool,
+    #[serde(default)]
+    pub starred: bool,
+    #[serde(skip)]
+    pub effects: RuleEffects,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RuleEffects {
+    pub fired: Vec<String>,
+    pub hidden: bool,
+    pub highlight: Option<Color>,
+    pub tags: Vec<String>,
+    pub notify: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -353,6 +733,10 @@ pub struct ItemData {
     pub title: Option<String>,
     pub content: Option<String>,
     pub summary: Option<String>,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
     pub media: Vec<MediaObject>,
     pub posted: Option<DateTime<Utc>>,
     pub links: Vec<Link>,
@@ -364,6 +748,16 @@ impl ItemData {
             title: item.title.map(|t| t.content),
             content: item.content.and_then(|s| s.body).map(html_to_text),
             summary: item.summary.map(|s| html_to_text(s.content)),
+            authors: item
+                .authors
+                .into_iter()
+                .map(|p| p.email.filter(|_| p.name == "author").unwrap_or(p.name))
+                .collect(),
+            categories: item
+                .categories
+                .into_iter()
+                .map(|c| c.label.unwrap_or(c.term))
+                .collect(),
             posted: item.published.or(item.updated),
             links: item.links.into_iter().map(Link).collect(),
             media: item.media.into_iter().map(MediaObject).collect(),
@@ -427,7 +821,7 @@ pub struct MediaContent(pub feed_rs::model::MediaContent);
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MediaObject(pub feed_rs::model::MediaObject);
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct FeedType(pub Option<feed_rs::model::FeedType>);
 impl Display for FeedType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -446,10 +840,12 @@ fn pretty_date(date: DateTime<Utc>) -> String {
     let delta_days = (Utc::now() - date).num_days();
     match delta_days {
         0 => HumanTime::from(date).to_text_en(Accuracy::Rough, Tense::Past),
-        _ if delta_days < CONFIG.relative_time_threshold as i64 => {
+        _ if delta_days < CONFIG.get().relative_time_threshold as i64 => {
             format!("{}, {}", HumanTime::from(date), date.format("%a, %H:%M"))
         }
-        _ => date.format(CONFIG.theme.date_format.as_str()).to_string(),
+        _ => date
+            .format(CONFIG.get().theme.date_format.as_str())
+            .to_string(),
     }
 }
 
@@ -463,6 +859,15 @@ fn html_to_text(html: String) -> String {
         .to_string()
 }
 
+// starred items are kept on top of the max_items most recent ones
+fn truncate_items(items: &mut Vec<Item>, max_items: usize) {
+    let mut kept = 0;
+    items.retain(|i| {
+        kept += usize::from(!i.state.starred);
+        i.state.starred || kept <= max_items
+    });
+}
+
 fn notify_new_items(summary: &str, items: &[Item]) {
     let _ = Notification::new()
         .summary(summary)
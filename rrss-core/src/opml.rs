@@ -0,0 +1,117 @@
+use std::io::Cursor;
+
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+
+pub const RRSS_NS: &str = "https://github.com/diegostafa/rrss";
+
+#[derive(Default, Debug, Clone)]
+pub struct Outline {
+    pub attrs: Vec<(String, String)>,
+    pub outlines: Vec<Outline>,
+}
+impl Outline {
+    pub fn new(text: &str) -> Self {
+        Self::default().with("text", text)
+    }
+    pub fn with(mut self, key: &str, value: &str) -> Self {
+        self.attrs.push((key.to_string(), value.to_string()));
+        self
+    }
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct Opml {
+    pub title: Option<String>,
+    pub outlines: Vec<Outline>,
+}
+impl Opml {
+    pub fn parse(content: &str) -> Result<Self, quick_xml::Error> {
+        let mut reader = Reader::from_str(content);
+        reader.config_mut().trim_text(true);
+
+        let mut opml = Self::default();
+        let mut stack: Vec<Outline> = vec![];
+        let mut in_title = false;
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) if e.name().as_ref() == b"outline" => stack.push(outline(&e)?),
+                Event::Empty(e) if e.name().as_ref() == b"outline" => {
+                    let outline = outline(&e)?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.outlines.push(outline),
+                        None => opml.outlines.push(outline),
+                    }
+                }
+                Event::End(e) if e.name().as_ref() == b"outline" => {
+                    if let Some(outline) = stack.pop() {
+                        match stack.last_mut() {
+                            Some(parent) => parent.outlines.push(outline),
+                            None => opml.outlines.push(outline),
+                        }
+                    }
+                }
+                Event::Start(e) if e.name().as_ref() == b"title" => in_title = true,
+                Event::End(e) if e.name().as_ref() == b"title" => in_title = false,
+                Event::Text(t) if in_title => opml.title = Some(t.unescape()?.to_string()),
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        Ok(opml)
+    }
+    pub fn to_xml(&self) -> std::io::Result<String> {
+        let mut writer = Writer::new_with_indent(Cursor::new(vec![]), b' ', 2);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+        let root =
+            BytesStart::new("opml").with_attributes([("version", "2.0"), ("xmlns:rrss", RRSS_NS)]);
+        writer.write_event(Event::Start(root))?;
+
+        writer.write_event(Event::Start(BytesStart::new("head")))?;
+        if let Some(title) = &self.title {
+            writer.write_event(Event::Start(BytesStart::new("title")))?;
+            writer.write_event(Event::Text(BytesText::new(title)))?;
+            writer.write_event(Event::End(BytesEnd::new("title")))?;
+        }
+        writer.write_event(Event::End(BytesEnd::new("head")))?;
+
+        writer.write_event(Event::Start(BytesStart::new("body")))?;
+        for outline in &self.outlines {
+            write_outline(&mut writer, outline)?;
+        }
+        writer.write_event(Event::End(BytesEnd::new("body")))?;
+        writer.write_event(Event::End(BytesEnd::new("opml")))?;
+        Ok(String::from_utf8_lossy(&writer.into_inner().into_inner()).into_owned())
+    }
+}
+
+fn outline(e: &BytesStart) -> Result<Outline, quick_xml::Error> {
+    let mut outline = Outline::default();
+    for attr in e.attributes() {
+        let attr = attr?;
+        outline.attrs.push((
+            String::from_utf8_lossy(attr.key.as_ref()).into_owned(),
+            attr.unescape_value()?.into_owned(),
+        ));
+    }
+    Ok(outline)
+}
+
+fn write_outline(writer: &mut Writer<Cursor<Vec<u8>>>, outline: &Outline) -> std::io::Result<()> {
+    let start = BytesStart::new("outline")
+        .with_attributes(outline.attrs.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+    if outline.outlines.is_empty() {
+        return writer.write_event(Event::Empty(start));
+    }
+    writer.write_event(Event::Start(start))?;
+    for child in &outline.outlines {
+        write_outline(writer, child)?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("outline")))
+}
@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use async_semaphore::Semaphore;
+use async_std::channel::{Receiver, Sender};
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::feed_manager::FetchResult;
+use crate::globals::CONFIG;
+use crate::models::{Feed, FeedId};
+
+const MAX_BACKOFF_EXP: u32 = 6;
+const MAX_INTERVAL: TimeDelta = TimeDelta::days(1);
+// bounds of the interval before any backoff, a ttl or a configured value
+// outside of them is clamped
+const MIN_BASE_INTERVAL: TimeDelta = TimeDelta::minutes(1);
+const MAX_BASE_INTERVAL: TimeDelta = TimeDelta::days(365);
+
+pub struct Scheduler {
+    running: HashSet<FeedId>,
+    semaphore: Arc<Semaphore>,
+    sx: Sender<FetchResult>,
+    rx: Receiver<FetchResult>,
+}
+impl Scheduler {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let (sx, rx) = async_std::channel::unbounded();
+        Self {
+            running: HashSet::new(),
+            semaphore: Arc::new(Semaphore::new(CONFIG.get().max_concurrency)),
+            sx,
+            rx,
+        }
+    }
+    pub fn due_feeds<'a>(&mut self, feeds: impl Iterator<Item = &'a Feed>) -> Vec<&'a Feed> {
+        let now = Utc::now();
+        let due = feeds
+            .filter(|f| !f.conf.manual_update && !self.running.contains(f.id()))
+            .filter(|f| self.next_update(f) <= now)
+            .collect::<Vec<_>>();
+        for feed in &due {
+            let _ = self.running.insert(feed.id().clone());
+        }
+        due
+    }
+    // based on the persisted state, so a restart doesn't retry failing feeds
+    // before their backoff is over
+    pub fn next_update(&self, feed: &Feed) -> DateTime<Utc> {
+        match feed.state.last_success.max(feed.state.last_failure) {
+            Some(last) => last + update_interval(feed),
+            None => DateTime::<Utc>::MIN_UTC,
+        }
+    }
+    pub fn finished(&mut self, id: &FeedId) {
+        let _ = self.running.remove(id);
+    }
+    pub fn semaphore(&self) -> Arc<Semaphore> {
+        self.semaphore.clone()
+    }
+    pub fn sender(&self) -> Sender<FetchResult> {
+        self.sx.clone()
+    }
+    pub fn receiver(&self) -> &Receiver<FetchResult> {
+        &self.rx
+    }
+}
+
+pub fn update_interval(feed: &Feed) -> TimeDelta {
+    let minutes = feed
+        .conf
+        .update_interval
+        .unwrap_or(CONFIG.get().update_interval)
+        .max(feed.data.as_ref().and_then(|d| d.ttl).unwrap_or_default() as u64);
+    let interval = i64::try_from(minutes)
+        .ok()
+        .and_then(TimeDelta::try_minutes)
+        .unwrap_or(MAX_BASE_INTERVAL)
+        .clamp(MIN_BASE_INTERVAL, MAX_BASE_INTERVAL);
+    let backoff = 2_i32.pow(feed.state.failure_streak.min(MAX_BACKOFF_EXP));
+    (interval * backoff).min(interval.max(MAX_INTERVAL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FeedSource;
+    use crate::models::FeedData;
+
+    fn feed(interval: u64, ttl: Option<u32>, failures: u32) -> Feed {
+        let mut feed = Feed::new(FeedSource::new(
+            FeedId("https://example.com/feed".into()),
+            vec![],
+        ));
+        feed.conf.update_interval = Some(interval);
+        feed.data = ttl.map(|ttl| FeedData {
+            ttl: Some(ttl),
+            ..Default::default()
+        });
+        feed.state.failure_streak = failures;
+        feed
+    }
+
+    #[test]
+    fn intervals_are_clamped() {
+        assert_eq!(update_interval(&feed(0, None, 0)), TimeDelta::minutes(1));
+        assert_eq!(
+            update_interval(&feed(30, Some(0), 0)),
+            TimeDelta::minutes(30)
+        );
+        assert_eq!(
+            update_interval(&feed(30, Some(120), 0)),
+            TimeDelta::minutes(120)
+        );
+        assert_eq!(update_interval(&feed(u64::MAX, None, 0)), MAX_BASE_INTERVAL);
+        assert_eq!(
+            update_interval(&feed(0, Some(u32::MAX), 3)),
+            MAX_BASE_INTERVAL
+        );
+    }
+
+    #[test]
+    fn backoff_starts_from_the_last_attempt() {
+        let scheduler = Scheduler::new();
+        let mut feed = feed(10, None, 0);
+        assert_eq!(scheduler.next_update(&feed), DateTime::<Utc>::MIN_UTC);
+
+        let now = Utc::now();
+        feed.state.failure_streak = 2;
+        feed.state.last_failure = Some(now);
+        assert_eq!(scheduler.next_update(&feed), now + TimeDelta::minutes(40));
+
+        feed.state.last_success = Some(now - TimeDelta::days(1));
+        assert_eq!(scheduler.next_update(&feed), now + TimeDelta::minutes(40));
+
+        feed.state.last_success = Some(now + TimeDelta::minutes(5));
+        feed.state.failure_streak = 0;
+        assert_eq!(scheduler.next_update(&feed), now + TimeDelta::minutes(15));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let minutes = |interval, failures| update_interval(&feed(interval, None, failures));
+        assert_eq!(minutes(10, 1), TimeDelta::minutes(20));
+        assert_eq!(minutes(10, 3), TimeDelta::minutes(80));
+        assert_eq!(minutes(10, MAX_BACKOFF_EXP), TimeDelta::minutes(640));
+        assert_eq!(minutes(10, 50), TimeDelta::minutes(640));
+        // never backs off past a day, unless the interval itself is longer
+        assert_eq!(minutes(60, 6), MAX_INTERVAL);
+        assert_eq!(minutes(60 * 48, 2), TimeDelta::hours(48));
+    }
+}
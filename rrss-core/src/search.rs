@@ -0,0 +1,550 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use ratatui::layout::Constraint;
+use ratatui::style::Style;
+use ratatui_helpers::stateful_table::Tabular;
+use regex::Regex;
+
+use crate::models::{Feed, FeedId, Item, ItemId};
+
+const SNIPPET_RADIUS: usize = 40;
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+
+lazy_static! {
+    static ref TOKEN: Regex = Regex::new(r"[\p{Alphabetic}\p{Nd}]+").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Field {
+    Title,
+    Author,
+    Summary,
+    Content,
+    Link,
+}
+impl Field {
+    fn weight(self) -> f32 {
+        match self {
+            Field::Title => 3.0,
+            Field::Author => 2.0,
+            Field::Summary => 1.5,
+            Field::Content | Field::Link => 1.0,
+        }
+    }
+}
+
+struct Posting {
+    field: Field,
+    positions: Vec<u32>,
+}
+
+struct Doc {
+    terms: Vec<String>,
+    len: usize,
+    fingerprint: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Clause {
+    Term(String),
+    Prefix(String),
+    Phrase(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query(Vec<Clause>);
+impl Query {
+    pub fn parse(query: &str) -> Self {
+        let mut clauses = vec![];
+        for (i, part) in query.split('"').enumerate() {
+            if i % 2 == 1 {
+                clauses.extend(Self::phrase(tokenize(part).collect()));
+                continue;
+            }
+            for word in part.split_whitespace() {
+                let tokens = tokenize(word).collect_vec();
+                match word.ends_with('*') && tokens.len() == 1 {
+                    true => clauses.push(Clause::Prefix(tokens[0].clone())),
+                    false => clauses.extend(Self::phrase(tokens)),
+                }
+            }
+        }
+        Self(clauses)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    fn phrase(mut tokens: Vec<String>) -> Option<Clause> {
+        match tokens.len() {
+            0 => None,
+            1 => Some(Clause::Term(tokens.remove(0))),
+            _ => Some(Clause::Phrase(tokens)),
+        }
+    }
+    fn matches_token(&self, token: &str) -> bool {
+        self.0.iter().any(|c| match c {
+            Clause::Term(t) => t == token,
+            Clause::Prefix(p) => token.starts_with(p.as_str()),
+            Clause::Phrase(words) => words[0] == token,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct SearchIndex {
+    terms: BTreeMap<String, HashMap<ItemId, Vec<Posting>>>,
+    docs: HashMap<ItemId, Doc>,
+    feeds: HashMap<FeedId, HashSet<ItemId>>,
+    total_len: usize,
+}
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn index_feed(&mut self, feed: &Feed) {
+        let items = feed.items().map(|items| items.as_slice()).unwrap_or(&[]);
+        let current = items.iter().map(|i| &i.data.id).collect::<HashSet<_>>();
+        let stale = self
+            .feeds
+            .get(feed.id())
+            .into_iter()
+            .flatten()
+            .filter(|id| !current.contains(id))
+            .cloned()
+            .collect_vec();
+        for id in stale {
+            self.remove(&id);
+        }
+        for item in items {
+            let fields = fields(item);
+            let fingerprint = fingerprint(&fields);
+            let id = &item.data.id;
+            if self
+                .docs
+                .get(id)
+                .is_some_and(|d| d.fingerprint == fingerprint)
+            {
+                continue;
+            }
+            self.remove(id);
+            self.add(id, fields, fingerprint);
+        }
+    }
+    pub fn remove_feed(&mut self, id: &FeedId) {
+        for item in self.feeds.remove(id).unwrap_or_default() {
+            self.remove(&item);
+        }
+    }
+    pub fn search(&self, query: &Query) -> Vec<(ItemId, f32)> {
+        if query.is_empty() || self.docs.is_empty() {
+            return vec![];
+        }
+        let avg_len = self.total_len as f32 / self.docs.len() as f32;
+        let mut scores: Option<HashMap<ItemId, f32>> = None;
+        for clause in &query.0 {
+            let hits = self.clause_hits(clause);
+            let idf = self.idf(hits.len());
+            let clause_scores = hits.into_iter().map(|(id, tf)| {
+                let len = self.docs.get(&id).map_or(0, |d| d.len) as f32;
+                let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * len / avg_len.max(1.0));
+                let score = idf * tf * (BM25_K1 + 1.0) / (tf + norm);
+                (id, score)
+            });
+            scores = Some(match scores {
+                None => clause_scores.collect(),
+                Some(mut scores) => {
+                    let clause_scores = clause_scores.collect::<HashMap<_, _>>();
+                    scores.retain(|id, _| clause_scores.contains_key(id));
+                    for (id, score) in scores.iter_mut() {
+                        *score += clause_scores[id];
+                    }
+                    scores
+                }
+            });
+        }
+        scores
+            .unwrap_or_default()
+            .into_iter()
+            .sorted_by(|a, b| b.1.total_cmp(&a.1))
+            .collect()
+    }
+
+    fn add(&mut self, id: &ItemId, fields: Vec<(Field, String)>, fingerprint: u64) {
+        let mut doc = Doc {
+            terms: vec![],
+            len: 0,
+            fingerprint,
+        };
+        let mut next_pos = HashMap::new();
+        for (field, text) in fields {
+            let pos = next_pos.entry(field).or_insert(0);
+            for token in tokenize(&text) {
+                let postings = self
+                    .terms
+                    .entry(token.clone())
+                    .or_default()
+                    .entry(id.clone())
+                    .or_default();
+                match postings.iter_mut().find(|p| p.field == field) {
+                    Some(posting) => posting.positions.push(*pos),
+                    None => postings.push(Posting {
+                        field,
+                        positions: vec![*pos],
+                    }),
+                }
+                doc.terms.push(token);
+                doc.len += 1;
+                *pos += 1;
+            }
+            // entries of the same field are apart, so phrases can't span them
+            *pos += 1;
+        }
+        doc.terms.sort();
+        doc.terms.dedup();
+        self.total_len += doc.len;
+        let _ = self.docs.insert(id.clone(), doc);
+        let _ = self
+            .feeds
+            .entry(FeedId(id.0.clone()))
+            .or_default()
+            .insert(id.clone());
+    }
+    fn remove(&mut self, id: &ItemId) {
+        let Some(doc) = self.docs.remove(id) else {
+            return;
+        };
+        self.total_len -= doc.len;
+        for term in doc.terms {
+            if let Some(postings) = self.terms.get_mut(&term) {
+                let _ = postings.remove(id);
+                if postings.is_empty() {
+                    let _ = self.terms.remove(&term);
+                }
+            }
+        }
+        if let Some(ids) = self.feeds.get_mut(&FeedId(id.0.clone())) {
+            let _ = ids.remove(id);
+        }
+    }
+    fn idf(&self, df: usize) -> f32 {
+        let n = self.docs.len() as f32;
+        let df = df as f32;
+        (1.0 + (n - df + 0.5) / (df + 0.5)).ln()
+    }
+    fn clause_hits(&self, clause: &Clause) -> HashMap<ItemId, f32> {
+        let mut hits = HashMap::new();
+        match clause {
+            Clause::Term(term) => {
+                for (id, postings) in self.terms.get(term).into_iter().flatten() {
+                    *hits.entry(id.clone()).or_default() += weighted_tf(postings);
+                }
+            }
+            Clause::Prefix(prefix) => {
+                let terms = self
+                    .terms
+                    .range(prefix.clone()..)
+                    .take_while(|(term, _)| term.starts_with(prefix.as_str()));
+                for (_, docs) in terms {
+                    for (id, postings) in docs {
+                        *hits.entry(id.clone()).or_default() += weighted_tf(postings);
+                    }
+                }
+            }
+            Clause::Phrase(words) => {
+                let Some(first) = self.terms.get(&words[0]) else {
+                    return hits;
+                };
+                for (id, postings) in first {
+                    let tf = postings
+                        .iter()
+                        .map(|p| {
+                            let count = p
+                                .positions
+                                .iter()
+                                .filter(|&&pos| self.phrase_at(id, p.field, pos, &words[1..]))
+                                .count();
+                            count as f32 * p.field.weight()
+                        })
+                        .sum::<f32>();
+                    if tf > 0.0 {
+                        let _ = hits.insert(id.clone(), tf);
+                    }
+                }
+            }
+        }
+        hits
+    }
+    fn phrase_at(&self, id: &ItemId, field: Field, start: u32, rest: &[String]) -> bool {
+        rest.iter().enumerate().all(|(i, word)| {
+            self.terms
+                .get(word)
+                .and_then(|docs| docs.get(id))
+                .and_then(|postings| postings.iter().find(|p| p.field == field))
+                .is_some_and(|p| p.positions.contains(&(start + i as u32 + 1)))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub item: Item,
+    pub feed: String,
+    pub snippet: String,
+    pub score: f32,
+}
+impl SearchHit {
+    pub fn new(item: Item, feed: String, query: &Query, score: f32) -> Self {
+        let data = &item.data;
+        let snippet = [&data.summary, &data.content, &data.title]
+            .into_iter()
+            .flatten()
+            .find_map(|text| snippet(text, query))
+            .or_else(|| {
+                data.summary
+                    .as_ref()
+                    .or(data.content.as_ref())
+                    .map(|text| excerpt(text, 0))
+            })
+            .unwrap_or_default();
+        Self {
+            item,
+            feed,
+            snippet,
+            score,
+        }
+    }
+}
+impl Tabular for SearchHit {
+    type Value = ItemId;
+    fn cmp_by_col(&self, other: &Self, col: usize) -> Ordering {
+        match col {
+            0 => self.feed.cmp(&other.feed),
+            1 => self.item.cmp_by_col(&other.item, 1),
+            2 => self.snippet.cmp(&other.snippet),
+            _ => panic!(),
+        }
+    }
+    fn value(&self) -> Self::Value {
+        self.item.data.id.clone()
+    }
+    fn content(&self) -> Vec<String> {
+        vec![
+            format!("{}", self.feed),
+            format!("{}", self.item.data.title.clone().unwrap_or_default()),
+            format!("{}", self.snippet),
+        ]
+    }
+    fn column_names() -> Option<Vec<String>> {
+        Some(vec![format!("Feed"), format!("Title"), format!("Match")])
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![Constraint::Length, Constraint::Length, Constraint::Fill]
+    }
+    fn style(&self) -> Style {
+        self.item.style()
+    }
+}
+
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    TOKEN.find_iter(text).map(|m| m.as_str().to_lowercase())
+}
+
+fn fields(item: &Item) -> Vec<(Field, String)> {
+    let data = &item.data;
+    let mut fields = vec![];
+    fields.extend(data.title.clone().map(|t| (Field::Title, t)));
+    fields.extend(data.authors.iter().map(|a| (Field::Author, a.clone())));
+    fields.extend(data.summary.clone().map(|s| (Field::Summary, s)));
+    fields.extend(data.content.clone().map(|c| (Field::Content, c)));
+    for link in &data.links {
+        fields.extend(link.0.title.clone().map(|t| (Field::Link, t)));
+        fields.push((Field::Link, link.0.href.clone()));
+    }
+    fields
+}
+
+fn fingerprint(fields: &[(Field, String)]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    fields.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn weighted_tf(postings: &[Posting]) -> f32 {
+    postings
+        .iter()
+        .map(|p| p.positions.len() as f32 * p.field.weight())
+        .sum()
+}
+
+fn snippet(text: &str, query: &Query) -> Option<String> {
+    TOKEN
+        .find_iter(text)
+        .find(|m| query.matches_token(&m.as_str().to_lowercase()))
+        .map(|m| excerpt(text, text[..m.start()].chars().count()))
+}
+
+fn excerpt(text: &str, at: usize) -> String {
+    let chars = text.chars().collect_vec();
+    let start = at.saturating_sub(SNIPPET_RADIUS);
+    let end = (at + SNIPPET_RADIUS).min(chars.len());
+    let excerpt = chars[start..end]
+        .iter()
+        .collect::<String>()
+        .split_whitespace()
+        .join(" ");
+    let prefix = if start > 0 { "..." } else { "" };
+    let suffix = if end < chars.len() { "..." } else { "" };
+    format!("{prefix}{excerpt}{suffix}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FeedSource;
+    use crate::models::{FeedData, ItemData, ItemState};
+
+    const URL: &str = "https://example.com/feed";
+
+    fn item(id: &str, title: &str, content: &str) -> Item {
+        Item {
+            data: ItemData {
+                id: ItemId(URL.into(), id.into()),
+                title: Some(title.into()),
+                content: Some(content.into()),
+                summary: None,
+                authors: vec![],
+                categories: vec![],
+                media: vec![],
+                posted: None,
+                links: vec![],
+            },
+            state: ItemState {
+                read_on: None,
+                is_filtered: false,
+                starred: false,
+                effects: Default::default(),
+            },
+        }
+    }
+    fn feed(items: Vec<Item>) -> Feed {
+        let mut feed = Feed::new(FeedSource::new(FeedId(URL.into()), vec![]));
+        feed.data = Some(FeedData {
+            items,
+            ..Default::default()
+        });
+        feed
+    }
+    fn index(items: Vec<Item>) -> SearchIndex {
+        let mut index = SearchIndex::new();
+        index.index_feed(&feed(items));
+        index
+    }
+    fn search(index: &SearchIndex, query: &str) -> Vec<String> {
+        let hits = index.search(&Query::parse(query));
+        hits.into_iter().map(|(id, _)| id.1).collect()
+    }
+
+    #[test]
+    fn titles_and_frequent_terms_rank_first() {
+        let index = index(vec![
+            item("content", "weekly news", "a post about rust"),
+            item("title", "rust weekly", "a post about news"),
+            item("twice", "news", "rust and more rust"),
+            item("none", "weekly news", "nothing to see"),
+        ]);
+        assert_eq!(search(&index, "rust"), vec!["title", "twice", "content"]);
+        assert_eq!(search(&index, "rust weekly"), vec!["title", "content"]);
+        assert!(search(&index, "missing").is_empty());
+        assert!(search(&index, "").is_empty());
+    }
+
+    #[test]
+    fn phrases_need_adjacent_terms() {
+        let mut authors = item("authors", "", "");
+        authors.data.authors = vec!["jane rust".into(), "async smith".into()];
+        let index = index(vec![
+            item("adjacent", "rust async runtimes", ""),
+            item("apart", "rust is not async", ""),
+            item("reversed", "async rust", ""),
+            authors,
+        ]);
+        assert_eq!(search(&index, "\"rust async\""), vec!["adjacent"]);
+        assert_eq!(search(&index, "rust async").len(), 4);
+        assert_eq!(
+            search(&index, "\"Rust, Async!\" runtimes"),
+            vec!["adjacent"]
+        );
+    }
+
+    #[test]
+    fn prefixes_match_the_start_of_terms() {
+        let index = index(vec![
+            item("programming", "programming", ""),
+            item("progress", "progress", ""),
+            item("aprog", "aprog", ""),
+        ]);
+        let mut hits = search(&index, "prog*");
+        hits.sort();
+        assert_eq!(hits, vec!["programming", "progress"]);
+        assert_eq!(search(&index, "progr* ming*").len(), 0);
+        assert_eq!(search(&index, "progra*"), vec!["programming"]);
+    }
+
+    #[test]
+    fn changed_and_removed_items_are_reindexed() {
+        let mut feed = feed(vec![
+            item("kept", "kept item", "unchanged"),
+            item("edited", "old title", ""),
+            item("removed", "removed item", ""),
+        ]);
+        let mut index = SearchIndex::new();
+        index.index_feed(&feed);
+        let total_len = index.total_len;
+
+        index.index_feed(&feed);
+        assert_eq!(index.total_len, total_len);
+
+        let items = feed.items_mut().unwrap();
+        items.retain(|i| i.data.id.1 != "removed");
+        items[1].data.title = Some("new title".into());
+        index.index_feed(&feed);
+        assert!(search(&index, "old").is_empty());
+        assert_eq!(search(&index, "new"), vec!["edited"]);
+        assert!(search(&index, "removed").is_empty());
+        assert_eq!(search(&index, "kept"), vec!["kept"]);
+        assert!(!index.terms.contains_key("removed"));
+
+        index.remove_feed(feed.id());
+        assert!(search(&index, "title").is_empty());
+        assert!(index.docs.is_empty());
+        assert_eq!(index.total_len, 0);
+    }
+
+    #[test]
+    fn snippets_surround_the_first_match() {
+        let text = format!("{} the match {}", "a".repeat(60), "b".repeat(60));
+        let hit = SearchHit::new(
+            item("1", "t", &text),
+            "feed".into(),
+            &Query::parse("match"),
+            1.0,
+        );
+        assert!(hit.snippet.starts_with("..."));
+        assert!(hit.snippet.ends_with("..."));
+        assert!(hit.snippet.contains("the match"));
+
+        let hit = SearchHit::new(
+            item("1", "t", "short text"),
+            "feed".into(),
+            &Query::parse("x"),
+            1.0,
+        );
+        assert_eq!(hit.snippet, "short text");
+    }
+}
@@ -18,12 +18,16 @@ impl Feed {
         Sorter(|a, b| a.feed_type().to_string().cmp(&b.feed_type().to_string()));
     pub const BY_HITS: Sorter<Self> = Sorter(|a, b| a.state.hits.cmp(&b.state.hits));
     pub const BY_TOT_UNREADS: Sorter<Self> = Sorter(|a, b| a.tot_unread().cmp(&b.tot_unread()));
+    pub const BY_FAILURES: Sorter<Self> =
+        Sorter(|a, b| a.state.failure_streak.cmp(&b.state.failure_streak));
 
     pub const BY_TITLE_REV: Sorter<Self> = Sorter(|b, a| a.name().cmp(&b.name()));
     pub const BY_LATEST_ITEM_REV: Sorter<Self> =
         Sorter(|b, a| a.state.latest_item_date.cmp(&b.state.latest_item_date));
     pub const BY_TYPE_REV: Sorter<Self> =
         Sorter(|b, a| a.feed_type().to_string().cmp(&b.feed_type().to_string()));
+    pub const BY_FAILURES_REV: Sorter<Self> =
+        Sorter(|b, a| a.state.failure_streak.cmp(&b.state.failure_streak));
 }
 impl Item {
     pub const BY_READ_ON: Sorter<Self> = Sorter(|a, b| a.state.read_on.cmp(&b.state.read_on));
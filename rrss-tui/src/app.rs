@@ -1,6 +1,7 @@
+use std::fmt::Display;
 use std::io::{self};
 use std::ops::Add;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
 use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
@@ -9,22 +10,33 @@ use ratatui::prelude::CrosstermBackend;
 use ratatui::widgets::TableState;
 use ratatui::Terminal;
 use ratatui_helpers::dock::{Dock, DockPosition};
-use ratatui_helpers::keymap::KeyMap;
 use ratatui_helpers::view_controller::ViewController;
-use rrss_core::feed_manager::{FeedManager, TaskStatus};
+use rrss_core::config::{check_config, config_modified, FeedSource};
+use rrss_core::discovery::FeedCandidate;
+use rrss_core::error::FetchError;
+use rrss_core::feed_manager::{FeedManager, FeedUpdate, TaskStatus};
 use rrss_core::filter::Filter;
+use rrss_core::globals::CONFIG;
 use rrss_core::models::{Feed, FeedId, Item, ItemId, Tag};
 use rrss_core::sorter::Sorter;
 
-use super::keymaps::{AppCommand, AppKeyMap};
+use super::history::{History, Snapshot};
+use super::keymaps::{AppCommand, AppKeyMap, Bindings};
 use super::views::detailed_item::DetailedItemView;
+use super::views::discovery::DiscoveryView;
+use super::views::errors::ErrorsView;
 use super::views::feeds::FeedsView;
+use super::views::health::HealthView;
 use super::views::help::HelpView;
 use super::views::items::ItemsView;
 use super::views::links::LinksView;
 use super::views::popup::PopupView;
-use super::views::prompt::PromptView;
+use super::views::prompt::{PromptKind, PromptView};
+use super::views::search::SearchView;
 use super::views::tags::TagView;
+use super::views::timeline::TimelineView;
+
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(PartialEq)]
 pub enum ViewKind {
@@ -33,7 +45,12 @@ pub enum ViewKind {
     Tags,
     Links,
     DetailedItem,
+    Errors,
+    Discovery,
+    Health,
     Prompt,
+    Search,
+    Timeline,
     Help,
     Notification,
     Quit,
@@ -49,22 +66,38 @@ pub enum AppRequest {
     OpenTagView(Filter, Sorter<Tag>),
     OpenFeedView(Filter, Sorter<Feed>),
     OpenItemsView(FeedId, Sorter<Item>),
+    OpenVirtualFeedView(String),
     OpenDetailedItemView(Filter, Sorter<Item>, usize),
     OpenLinksView(Filter),
     OpenPopupView(String),
+    OpenErrorsView(Vec<FetchError>),
+    OpenDiscoveryView(Vec<FeedCandidate>, Vec<String>),
+    OpenHealthView,
+    OpenSearchView,
+    OpenTimelineView(Filter),
     OpenHelpView,
     OpenInfoFeedView(FeedId),
     OpenInfoItemView(ItemId),
-    OpenSearchDock,
-    CloseSearchDock,
+    OpenPromptDock(PromptKind),
+    ClosePromptDock,
     SubmitPromptValue(String),
+    SubmitPromptAction(PromptKind, String),
     ChangePromptValue(String),
     UpdateFeeds(Filter),
     UpdateFeed(FeedId),
+    DiscoverFeeds(String, Vec<String>),
+    AddFeed(FeedId, Vec<String>),
     OpenItem(ItemId),
     MarkItemAsRead(ItemId),
+    ToggleItemRead(ItemId),
+    ToggleItemStarred(ItemId),
     MarkFeedAsRead(FeedId),
+    MarkTagAsRead(String),
+    MarkVirtualFeedAsRead(String),
     ClearFeed(FeedId),
+    Undo,
+    Redo,
+    Reload,
 }
 impl AppRequest {
     fn or_else<T: FnOnce() -> Self>(self, other: T) -> Self {
@@ -99,18 +132,38 @@ impl Add for AppRequest {
 pub struct App {
     fm: FeedManager,
     vc: ViewController<FeedManager, AppRequest, ViewKind>,
-    keymap: AppKeyMap,
+    keymap: Bindings<AppCommand>,
+    discover_tags: Vec<String>,
+    history: History,
+    config_modified: Option<SystemTime>,
+    config_checked: Instant,
 }
 impl App {
-    pub fn new(fm: FeedManager) -> Self {
+    pub fn new(mut fm: FeedManager) -> Self {
+        if CONFIG.get().auto_update {
+            fm.start_scheduler();
+        }
         Self {
             fm,
             vc: ViewController::new(Duration::from_secs(3)),
-            keymap: KeyMap::default(),
+            keymap: Bindings::new::<AppKeyMap>(),
+            discover_tags: vec![],
+            history: History::default(),
+            config_modified: config_modified(),
+            config_checked: Instant::now(),
         }
     }
     pub fn init(mut self) -> Self {
         self.handle_request(AppRequest::OpenFeedView(Filter::new(), Sorter::NONE));
+        if let Some(warning) = self.fm.cache_warning() {
+            let req = AppRequest::OpenPopupView(warning.to_string());
+            self.handle_request(req);
+        }
+        let errors = check_config();
+        if !errors.is_empty() {
+            let msg = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+            self.handle_request(AppRequest::OpenPopupView(msg.join("\n")));
+        }
         self
     }
     pub fn run(mut self) -> Result<(), Box<io::Error>> {
@@ -118,7 +171,7 @@ impl App {
         self.vc.curr().set_title();
         while self.vc.is_running() {
             let _ = term.draw(|f| self.vc.draw(f, f.area()))?;
-            let req = self.update() + self.poll_tasks();
+            let req = self.update() + self.poll_tasks() + self.poll_config();
             self.handle_request(req);
             self.vc.update_status_line();
         }
@@ -137,7 +190,14 @@ impl App {
                         Some(cmd) => match cmd {
                             AppCommand::QuitView => AppRequest::CloseView,
                             AppCommand::Help => AppRequest::OpenHelpView,
-                            AppCommand::Search => AppRequest::OpenSearchDock,
+                            AppCommand::Search => AppRequest::OpenPromptDock(PromptKind::Search),
+                            AppCommand::GlobalSearch => {
+                                AppRequest::OpenSearchView
+                                    + AppRequest::OpenPromptDock(PromptKind::SearchAll)
+                            }
+                            AppCommand::Undo => AppRequest::Undo,
+                            AppCommand::Redo => AppRequest::Redo,
+                            AppCommand::Reload => AppRequest::Reload,
                         },
                     },
                     _ => AppRequest::None,
@@ -147,30 +207,122 @@ impl App {
         AppRequest::None
     }
 
+    fn poll_config(&mut self) -> AppRequest {
+        // the loop ticks on every event, so the files are only checked
+        // once per interval
+        if !CONFIG.get().watch_config || self.config_checked.elapsed() < CONFIG_POLL_INTERVAL {
+            return AppRequest::None;
+        }
+        self.config_checked = Instant::now();
+        let modified = config_modified();
+        if modified == self.config_modified {
+            return AppRequest::None;
+        }
+        self.config_modified = modified;
+        AppRequest::Reload
+    }
     fn poll_tasks(&mut self) -> AppRequest {
         let r1 = match self.fm.poll_update_feed() {
             TaskStatus::Error(e) => {
                 self.vc.show_status(e);
                 AppRequest::None
             }
-            TaskStatus::Done(_) => AppRequest::RefreshView,
+            TaskStatus::Done(FeedUpdate::Unchanged) => {
+                self.vc.show_status("Feed not modified".into());
+                AppRequest::RefreshView
+            }
+            TaskStatus::Done(FeedUpdate::Updated) => AppRequest::RefreshView,
             _ => AppRequest::None,
         };
         let r2 = match self.fm.poll_update_feeds() {
             TaskStatus::Error(e) => AppRequest::OpenPopupView(format!("{:?}", e)),
-            TaskStatus::Done((errs, _)) => {
+            TaskStatus::Done(errs) => {
                 if errs.is_empty() {
                     AppRequest::RefreshView
                 } else {
-                    AppRequest::RefreshView + AppRequest::OpenPopupView(format!("{:?}", errs))
+                    AppRequest::RefreshView + AppRequest::OpenErrorsView(errs)
+                }
+            }
+            _ => AppRequest::None,
+        };
+        let r3 = match self.fm.poll_scheduler() {
+            TaskStatus::Error(e) => {
+                self.vc.show_status(e);
+                AppRequest::None
+            }
+            TaskStatus::Done((_, errs)) => {
+                if !errs.is_empty() {
+                    self.vc
+                        .show_status(format!("[auto update] {} feeds failed", errs.len()));
+                }
+                AppRequest::RefreshView
+            }
+            _ => AppRequest::None,
+        };
+        let r4 = match self.fm.poll_discover_feeds() {
+            TaskStatus::Error(e) => {
+                self.vc.show_status(e);
+                AppRequest::None
+            }
+            TaskStatus::Done(mut candidates) => {
+                let tags = std::mem::take(&mut self.discover_tags);
+                match candidates.len() {
+                    0 => {
+                        self.vc.show_status("No feeds found".into());
+                        AppRequest::None
+                    }
+                    1 => AppRequest::AddFeed(candidates.remove(0).url, tags),
+                    _ => AppRequest::OpenDiscoveryView(candidates, tags),
                 }
             }
             _ => AppRequest::None,
         };
-        r1 + r2
+        r1 + r2 + r3 + r4
+    }
+
+    fn snapshot(&self, req: &AppRequest) -> Option<Snapshot> {
+        let filter = match req {
+            AppRequest::MarkItemAsRead(id)
+            | AppRequest::ToggleItemRead(id)
+            | AppRequest::ToggleItemStarred(id) => Filter::new().item_id(id.clone()),
+            AppRequest::MarkFeedAsRead(id) => Filter::new().feed_id(id.clone()),
+            AppRequest::MarkTagAsRead(tag) => Filter::new().tag_id(tag.clone()),
+            AppRequest::MarkVirtualFeedAsRead(name) => self.fm.get_search(name)?.filter.clone(),
+            AppRequest::ClearFeed(id) => return Snapshot::feed(&self.fm, id.clone()),
+            AppRequest::SubmitPromptAction(PromptKind::TagFeed(id), tag)
+                if !tag.trim().is_empty() =>
+            {
+                return Snapshot::tags(&self.fm, id.clone())
+            }
+            _ => return None,
+        };
+        Some(match req {
+            AppRequest::ToggleItemRead(_) | AppRequest::ToggleItemStarred(_) => {
+                Snapshot::items(&self.fm, &filter)
+            }
+            _ => Snapshot::unread_items(&self.fm, &filter),
+        })
+    }
+
+    // the snapshot is taken before the request runs, but only kept once it
+    // succeeded, so a failed action can't be undone or drop the redo history
+    fn record<E: Display>(&mut self, snapshot: Option<Snapshot>, res: Result<(), E>) -> bool {
+        match res {
+            Ok(()) => {
+                if let Some(snapshot) = snapshot {
+                    self.history.record(snapshot);
+                }
+                true
+            }
+            Err(e) => {
+                self.vc.show_status(e.to_string());
+                false
+            }
+        }
     }
 
     fn handle_request(&mut self, req: AppRequest) {
+        let snapshot = self.snapshot(&req);
         match req {
             AppRequest::None => {}
             AppRequest::Chain(reqs) => reqs.into_iter().for_each(|req| self.handle_request(req)),
@@ -194,6 +346,16 @@ impl App {
                     TableState::new().with_selected(0),
                 )));
             }
+            AppRequest::OpenVirtualFeedView(name) => {
+                if let Some(search) = self.fm.get_search(&name).cloned() {
+                    self.vc.push(Box::new(ItemsView::new(
+                        &self.fm,
+                        search.filter,
+                        search.sorter,
+                        TableState::new().with_selected(0),
+                    )));
+                }
+            }
             AppRequest::OpenTagView(filter, sorter) => self.vc.push(Box::new(TagView::new(
                 &self.fm,
                 filter,
@@ -202,8 +364,15 @@ impl App {
             ))),
             AppRequest::OpenDetailedItemView(filter, sorter, idx) => {
                 let items = self.fm.get_items(&filter, &sorter);
-                let item = items.get(idx).unwrap();
-                let _ = self.fm.mark_item_as_read(item.data.id.clone());
+                let Some(item) = items.get(idx) else {
+                    return;
+                };
+                if CONFIG.get().mark_read_on_open && item.state.read_on.is_none() {
+                    let filter = Filter::new().item_id(item.data.id.clone());
+                    let snapshot = Snapshot::items(&self.fm, &filter);
+                    let res = self.fm.mark_item_as_read(item.data.id.clone());
+                    let _ = self.record(Some(snapshot), res);
+                }
                 let view = DetailedItemView::new(items, idx);
                 self.vc.push(Box::new(view));
             }
@@ -211,14 +380,33 @@ impl App {
                 self.fm.get_links(&filter, &Sorter::NONE),
             ))),
             AppRequest::OpenPopupView(msg) => self.vc.push(Box::new(PopupView::new(msg))),
+            AppRequest::OpenErrorsView(errs) => self.vc.push(Box::new(ErrorsView::new(errs))),
+            AppRequest::OpenDiscoveryView(candidates, tags) => {
+                self.vc.push(Box::new(DiscoveryView::new(candidates, tags)))
+            }
+            AppRequest::OpenHealthView => self.vc.push(Box::new(HealthView::new(
+                &self.fm,
+                TableState::new().with_selected(0),
+            ))),
+            AppRequest::OpenSearchView => self.vc.push(Box::new(SearchView::new(
+                &self.fm,
+                String::new(),
+                TableState::new().with_selected(0),
+            ))),
+            AppRequest::OpenTimelineView(filter) => self.vc.push(Box::new(TimelineView::new(
+                &self.fm,
+                filter,
+                TableState::new().with_selected(0),
+            ))),
             AppRequest::OpenHelpView => self.vc.push(Box::new(HelpView::default())),
 
             AppRequest::OpenInfoFeedView(feed_id) => {
                 if let Some(f) = self.fm.get_feed(feed_id) {
                     // todo: display things nicely
                     self.handle_request(AppRequest::OpenPopupView(format!(
-                        "bytes: {}\nconf: {:?}\ndata: {:?}",
+                        "bytes: {} (saved: {})\nconf: {:?}\ndata: {:?}",
                         f.state.exchanged_bytes,
+                        f.state.saved_bytes,
                         f.conf,
                         f.data.as_ref().map(|d| &d.links)
                     )));
@@ -228,10 +416,12 @@ impl App {
                 if let Some(i) = self.fm.get_item(item_id) {
                     // todo: display things nicely
                     self.handle_request(AppRequest::OpenPopupView(format!(
-                        "id: {:?}\ntitle: {}\nfiltered: {}",
+                        "id: {:?}\ntitle: {}\nfiltered: {}\nrules: {:?}\ntags: {:?}",
                         i.data.id,
                         i.data.title.clone().unwrap(),
                         i.state.is_filtered,
+                        i.state.effects.fired,
+                        i.state.effects.tags,
                     )));
                 }
             }
@@ -261,12 +451,59 @@ impl App {
                 };
                 let _ = self.fm.update_feed(feed_id, finally);
             }
+            AppRequest::DiscoverFeeds(url, tags) => {
+                if let TaskStatus::Running = self.fm.poll_discover_feeds() {
+                    return;
+                }
+                let id = self
+                    .vc
+                    .show_status_always(format!("Looking for feeds: {url}"));
+                let finally = {
+                    let status = self.vc.status().clone();
+                    move || status.lock().unwrap().remove(id)
+                };
+                self.discover_tags = tags;
+                drop(self.fm.discover_feeds(url, finally))
+            }
+            AppRequest::AddFeed(feed_id, tags) => {
+                match self.fm.add_feed(FeedSource::new(feed_id.clone(), tags)) {
+                    Ok(()) => {
+                        self.vc.show_status(format!("Added: {}", feed_id.0));
+                        self.handle_request(AppRequest::RefreshView);
+                    }
+                    Err(e) => self.vc.show_status(e.to_string()),
+                }
+            }
             AppRequest::MarkItemAsRead(item_id) => {
-                let _ = self.fm.mark_item_as_read(item_id);
+                let res = self.fm.mark_item_as_read(item_id);
+                let _ = self.record(snapshot, res);
+                self.handle_request(AppRequest::RefreshView);
+            }
+            AppRequest::ToggleItemRead(item_id) => {
+                let res = self.fm.toggle_item_read(item_id);
+                let _ = self.record(snapshot, res);
+                self.handle_request(AppRequest::RefreshView);
+            }
+            AppRequest::ToggleItemStarred(item_id) => {
+                let res = self.fm.toggle_item_starred(item_id);
+                let _ = self.record(snapshot, res);
                 self.handle_request(AppRequest::RefreshView);
             }
             AppRequest::MarkFeedAsRead(feed_id) => {
-                let _ = self.fm.mark_feed_as_read(feed_id);
+                let res = self.fm.mark_feed_as_read(feed_id);
+                let _ = self.record(snapshot, res);
+                self.handle_request(AppRequest::RefreshView);
+            }
+            AppRequest::MarkTagAsRead(tag) => {
+                let res = self.fm.mark_tag_as_read(tag);
+                let _ = self.record(snapshot, res);
+                self.handle_request(AppRequest::RefreshView);
+            }
+            AppRequest::MarkVirtualFeedAsRead(name) => {
+                if let Some(search) = self.fm.get_search(&name).cloned() {
+                    let res = self.fm.mark_items_as_read(&search.filter);
+                    let _ = self.record(snapshot, res);
+                }
                 self.handle_request(AppRequest::RefreshView);
             }
             AppRequest::OpenItem(item_id) => {
@@ -279,27 +516,98 @@ impl App {
                     }
                 }
             }
-            AppRequest::CloseSearchDock => self.vc.remove_dock(),
-            AppRequest::OpenSearchDock => self.vc.set_dock(Dock {
+            AppRequest::ClosePromptDock => self.vc.remove_dock(),
+            AppRequest::OpenPromptDock(kind) => self.vc.set_dock(Dock {
                 position: DockPosition::Bottom,
                 size: 1,
-                view: Box::new(PromptView::new("Search for: ".to_string())),
+                view: Box::new(PromptView::new(kind)),
             }),
 
             AppRequest::SubmitPromptValue(value) => {
                 let req = self.vc.curr_mut().on_prompt_submit(value);
                 self.handle_request(req);
             }
+            AppRequest::SubmitPromptAction(kind, value) => {
+                let res = match kind {
+                    PromptKind::Search | PromptKind::SearchAll => Ok(()),
+                    PromptKind::AddFeed => {
+                        let mut args = value.split_whitespace().map(str::to_string);
+                        if let Some(url) = args.next() {
+                            let req = AppRequest::DiscoverFeeds(url, args.collect());
+                            self.handle_request(req);
+                        }
+                        return;
+                    }
+                    PromptKind::RemoveFeed(feed_id) => match value.trim() {
+                        "y" | "Y" => self.fm.remove_feed(feed_id),
+                        _ => Ok(()),
+                    },
+                    PromptKind::ClearFeed(feed_id) => {
+                        if let "y" | "Y" = value.trim() {
+                            self.handle_request(AppRequest::ClearFeed(feed_id));
+                        }
+                        return;
+                    }
+                    PromptKind::TagFeed(feed_id) => match value.trim() {
+                        "" => Ok(()),
+                        tag => self.fm.tag_feed(feed_id, tag),
+                    },
+                    PromptKind::SetMaxItems(feed_id) => match value.trim().parse() {
+                        Ok(max_items) => self.fm.set_max_items(feed_id, max_items),
+                        Err(e) => Err(e.into()),
+                    },
+                };
+                if self.record(snapshot, res) {
+                    self.handle_request(AppRequest::RefreshView);
+                }
+            }
             AppRequest::ChangePromptValue(value) => {
                 let req = self.vc.curr_mut().on_prompt_change(value);
                 self.handle_request(req + AppRequest::RefreshView);
             }
             AppRequest::ClearFeed(feed_id) => {
-                if let Some(f) = self.fm.get_feed_mut(feed_id) {
-                    f.clear_data();
+                let res = self.fm.clear_feed(feed_id);
+                if self.record(snapshot, res) {
                     self.handle_request(AppRequest::RefreshView);
                 }
             }
+            AppRequest::Undo => {
+                match self.history.undo(&mut self.fm) {
+                    Ok(msg) => self.vc.show_status(msg),
+                    Err(e) => self.vc.show_status(e.to_string()),
+                }
+                self.handle_request(AppRequest::RefreshView);
+            }
+            AppRequest::Redo => {
+                match self.history.redo(&mut self.fm) {
+                    Ok(msg) => self.vc.show_status(msg),
+                    Err(e) => self.vc.show_status(e.to_string()),
+                }
+                self.handle_request(AppRequest::RefreshView);
+            }
+            AppRequest::Reload => {
+                let errors = match self.fm.reload() {
+                    Ok(errors) => errors,
+                    Err(e) => {
+                        self.vc.show_status(format!("[reload error] {e}"));
+                        return;
+                    }
+                };
+                self.keymap = Bindings::new::<AppKeyMap>();
+                self.config_modified = config_modified();
+                if CONFIG.get().auto_update {
+                    self.fm.start_scheduler();
+                } else {
+                    self.fm.stop_scheduler();
+                }
+                self.handle_request(AppRequest::RefreshView);
+                if errors.is_empty() {
+                    self.vc.show_status("Reloaded config and sources".into());
+                } else {
+                    let msg = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                    self.handle_request(AppRequest::OpenPopupView(msg.join("\n")));
+                }
+            }
         }
     }
 }
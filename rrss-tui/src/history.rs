@@ -0,0 +1,115 @@
+use rrss_core::feed_manager::FeedManager;
+use rrss_core::filter::Filter;
+use rrss_core::models::{Feed, FeedId, ItemId, ItemState};
+use rrss_core::sorter::Sorter;
+
+const MAX_HISTORY: usize = 100;
+
+#[derive(Clone)]
+pub enum Snapshot {
+    Items(Vec<(ItemId, ItemState)>),
+    Feed(Box<Feed>),
+    Tags(FeedId, Vec<String>),
+}
+impl Snapshot {
+    pub fn items(fm: &FeedManager, filter: &Filter) -> Self {
+        let states = fm
+            .get_items(filter, &Sorter::NONE)
+            .into_iter()
+            .map(|i| (i.data.id, i.state))
+            .collect();
+        Snapshot::Items(states)
+    }
+    // marking as read only changes the unread items, so marks that change
+    // nothing record an empty snapshot, which is dropped
+    pub fn unread_items(fm: &FeedManager, filter: &Filter) -> Self {
+        let states = fm
+            .get_items(filter, &Sorter::NONE)
+            .into_iter()
+            .filter(|i| i.state.read_on.is_none())
+            .map(|i| (i.data.id, i.state))
+            .collect();
+        Snapshot::Items(states)
+    }
+    pub fn feed(fm: &FeedManager, id: FeedId) -> Option<Self> {
+        Some(Snapshot::Feed(Box::new(fm.get_feed(id)?.clone())))
+    }
+    pub fn tags(fm: &FeedManager, id: FeedId) -> Option<Self> {
+        let tags = fm.get_feed(id.clone())?.conf.tags.clone();
+        Some(Snapshot::Tags(id, tags))
+    }
+    fn current(&self, fm: &FeedManager) -> Option<Self> {
+        match self {
+            Snapshot::Items(states) => {
+                let states = states
+                    .iter()
+                    .filter_map(|(id, _)| {
+                        Some((id.clone(), fm.get_item(id.clone())?.state.clone()))
+                    })
+                    .collect();
+                Some(Snapshot::Items(states))
+            }
+            Snapshot::Feed(feed) => Self::feed(fm, feed.id().clone()),
+            Snapshot::Tags(id, _) => Self::tags(fm, id.clone()),
+        }
+    }
+    fn restore(self, fm: &mut FeedManager) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Snapshot::Items(states) => Ok(fm.set_items_state(states)?),
+            Snapshot::Feed(feed) => {
+                let feed = *feed;
+                Ok(fm.restore_feed(feed.id().clone(), feed.state, feed.data)?)
+            }
+            Snapshot::Tags(id, tags) => fm.set_tags(id, tags),
+        }
+    }
+    fn describe(&self) -> String {
+        match self {
+            Snapshot::Items(states) => format!("state of {} items", states.len()),
+            Snapshot::Feed(feed) => format!("data of {}", feed.id().0),
+            Snapshot::Tags(id, _) => format!("tags of {}", id.0),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+impl History {
+    pub fn record(&mut self, snapshot: Snapshot) {
+        if let Snapshot::Items(states) = &snapshot
+            && states.is_empty()
+        {
+            return;
+        }
+        self.undo.push(snapshot);
+        if self.undo.len() > MAX_HISTORY {
+            let _ = self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+    pub fn undo(&mut self, fm: &mut FeedManager) -> Result<String, Box<dyn std::error::Error>> {
+        let snapshot = self.undo.pop().ok_or("Nothing to undo")?;
+        let msg = format!("Undone: {}", snapshot.describe());
+        Self::swap(fm, snapshot, &mut self.redo)?;
+        Ok(msg)
+    }
+    pub fn redo(&mut self, fm: &mut FeedManager) -> Result<String, Box<dyn std::error::Error>> {
+        let snapshot = self.redo.pop().ok_or("Nothing to redo")?;
+        let msg = format!("Redone: {}", snapshot.describe());
+        Self::swap(fm, snapshot, &mut self.undo)?;
+        Ok(msg)
+    }
+    fn swap(
+        fm: &mut FeedManager,
+        snapshot: Snapshot,
+        inverse: &mut Vec<Snapshot>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(current) = snapshot.current(fm) {
+            inverse.push(current);
+        }
+        snapshot.restore(fm)
+    }
+}
@@ -1,12 +1,106 @@
-use std::fmt::Display;
+use std::fmt::{Debug, Display};
 
 use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui_helpers::keymap::{KeyMap, ShortCut};
+use rrss_core::globals::CONFIG;
+use rrss_core::keybinds::{keys_to_string, normalize, KeySequence};
 
-#[derive(Clone)]
+// a keymap that can be overridden by the `[keybinds.<VIEW>]` config section
+pub trait ViewKeyMap: KeyMap {
+    const VIEW: &'static str;
+}
+
+// the effective shortcuts of a view, keys bound in the config replace the
+// default ones of the same command
+pub struct Bindings<C> {
+    shortcuts: Vec<(C, Vec<KeySequence>)>,
+    pending: KeySequence,
+}
+impl<C: Clone + Debug> Bindings<C> {
+    pub fn new<K: ViewKeyMap<Command = C>>() -> Self {
+        let shortcuts = K::default()
+            .get_shortcuts()
+            .iter()
+            .map(|ShortCut(cmd, keys)| {
+                let name = command_name(cmd);
+                let keys = match CONFIG
+                    .get()
+                    .keybinds
+                    .iter()
+                    .find(|kb| kb.view == K::VIEW && kb.command == name)
+                {
+                    Some(kb) => kb.keys.clone(),
+                    None => keys.iter().map(|k| vec![*k]).collect(),
+                };
+                (cmd.clone(), keys)
+            })
+            .collect();
+        Self {
+            shortcuts,
+            pending: vec![],
+        }
+    }
+    pub fn get_command(&mut self, ev: &KeyEvent) -> Option<C> {
+        self.pending.push(*ev);
+        let mut is_prefix = false;
+        for (cmd, keys) in &self.shortcuts {
+            for seq in keys {
+                if same_keys(seq, &self.pending) {
+                    self.pending.clear();
+                    return Some(cmd.clone());
+                }
+                is_prefix |= seq.len() > self.pending.len()
+                    && same_keys(&seq[..self.pending.len()], &self.pending);
+            }
+        }
+        if !is_prefix {
+            // the key may still start another sequence
+            let retry = self.pending.len() > 1;
+            self.pending.clear();
+            if retry {
+                return self.get_command(ev);
+            }
+        }
+        None
+    }
+    pub fn rows(&self) -> Vec<(String, String)> {
+        self.shortcuts
+            .iter()
+            .map(|(cmd, keys)| {
+                let keys = keys.iter().map(keys_to_string).collect::<Vec<_>>();
+                (command_name(cmd), keys.join(", "))
+            })
+            .collect()
+    }
+}
+
+fn same_keys(a: &[KeyEvent], b: &[KeyEvent]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(a, b)| normalize(a.code, a.modifiers) == normalize(b.code, b.modifiers))
+}
+
+// the name used in the config, `UpdateFeeds` becomes `update_feeds`
+pub fn command_name(cmd: &impl Debug) -> String {
+    let mut name = String::new();
+    for c in format!("{cmd:?}").chars() {
+        if c.is_uppercase() && !name.is_empty() {
+            name.push('_');
+        }
+        name.extend(c.to_lowercase());
+    }
+    name
+}
+
+#[derive(Debug, Clone)]
 pub enum AppCommand {
     QuitView,
     Search,
+    GlobalSearch,
+    Undo,
+    Redo,
+    Reload,
     Help,
 }
 impl Display for AppCommand {
@@ -15,11 +109,18 @@ impl Display for AppCommand {
             AppCommand::QuitView => write!(f, "quit view"),
             AppCommand::Help => write!(f, "help"),
             AppCommand::Search => write!(f, "search"),
+            AppCommand::GlobalSearch => write!(f, "search all items"),
+            AppCommand::Undo => write!(f, "undo"),
+            AppCommand::Redo => write!(f, "redo"),
+            AppCommand::Reload => write!(f, "reload config and sources"),
         }
     }
 }
 
 pub struct AppKeyMap(pub Vec<ShortCut<AppCommand>>);
+impl ViewKeyMap for AppKeyMap {
+    const VIEW: &'static str = "global";
+}
 impl KeyMap for AppKeyMap {
     type Command = AppCommand;
     fn get_shortcuts(&self) -> &[ShortCut<Self::Command>] {
@@ -46,6 +147,67 @@ impl KeyMap for AppKeyMap {
                 AppCommand::Search,
                 vec![KeyEvent::new(KeyCode::Char('/'), KeyModifiers::NONE)],
             ),
+            ShortCut(
+                AppCommand::GlobalSearch,
+                vec![KeyEvent::new(KeyCode::Char('f'), KeyModifiers::CONTROL)],
+            ),
+            ShortCut(
+                AppCommand::Undo,
+                vec![KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                AppCommand::Redo,
+                vec![KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL)],
+            ),
+            ShortCut(
+                AppCommand::Reload,
+                vec![KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE)],
+            ),
         ])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rrss_core::keybinds::VIEW_COMMANDS;
+
+    use super::*;
+    use crate::views::detailed_item::DetailedItemKeyMap;
+    use crate::views::discovery::DiscoveryKeyMap;
+    use crate::views::errors::ErrorsKeyMap;
+    use crate::views::feeds::FeedsKeyMap;
+    use crate::views::health::HealthKeyMap;
+    use crate::views::items::ItemsKeyMap;
+    use crate::views::links::LinksKeyMap;
+    use crate::views::tags::TagsKeyMap;
+    use crate::widgets::scrollable_paragraph::ScrollableParagraphKeyMap;
+
+    fn commands<K: ViewKeyMap>() -> (&'static str, Vec<String>) {
+        let shortcuts = K::default();
+        let names = shortcuts.get_shortcuts().iter().map(|s| command_name(&s.0));
+        (K::VIEW, names.collect())
+    }
+
+    // the config is checked against the table in rrss-core, which has to
+    // follow the keymaps
+    #[test]
+    fn view_commands_match_the_keymaps() {
+        let keymaps = vec![
+            commands::<AppKeyMap>(),
+            commands::<FeedsKeyMap>(),
+            commands::<ItemsKeyMap>(),
+            commands::<DetailedItemKeyMap>(),
+            commands::<ScrollableParagraphKeyMap>(),
+            commands::<TagsKeyMap>(),
+            commands::<LinksKeyMap>(),
+            commands::<ErrorsKeyMap>(),
+            commands::<HealthKeyMap>(),
+            commands::<DiscoveryKeyMap>(),
+        ];
+        let table = VIEW_COMMANDS
+            .iter()
+            .map(|(view, commands)| (*view, commands.iter().map(|c| c.to_string()).collect()))
+            .collect::<Vec<_>>();
+        assert_eq!(keymaps, table);
+    }
+}
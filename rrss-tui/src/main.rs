@@ -7,11 +7,19 @@ use app::App;
 use rrss_core::feed_manager::FeedManager;
 
 pub mod app;
+pub mod history;
 pub mod keymaps;
 pub mod theme;
 pub mod views;
 pub mod widgets;
 
 fn main() {
-    App::new(FeedManager::new()).init().run().unwrap()
+    let fm = match FeedManager::new() {
+        Ok(fm) => fm,
+        Err(e) => {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+    };
+    App::new(fm).init().run().unwrap()
 }
@@ -7,8 +7,8 @@ pub struct StyledWidget;
 impl StyledWidget {
     pub fn header_paragraph<'a>(s: String) -> Paragraph<'a> {
         Paragraph::new(s)
-            .fg(CONFIG.theme.fg_item_header)
-            .bg(CONFIG.theme.bg_item_header)
+            .fg(CONFIG.get().theme.fg_item_header)
+            .bg(CONFIG.get().theme.bg_item_header)
     }
     pub fn table<'a, T: Tabular>(
         data: Vec<T>,
@@ -26,19 +26,19 @@ impl StyledWidget {
     }
     pub fn block<'a>() -> Block<'a> {
         let mut block = Block::new();
-        if CONFIG.theme.borders {
+        if CONFIG.get().theme.borders {
             block = block
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(CONFIG.theme.border_color))
+                .border_style(Style::default().fg(CONFIG.get().theme.border_color))
         }
-        if CONFIG.theme.rounded_borders {
+        if CONFIG.get().theme.rounded_borders {
             block = block.border_type(BorderType::Rounded)
         }
         block
     }
     fn table_padding() -> Padding {
         let mut padding = Padding::default();
-        if CONFIG.theme.borders {
+        if CONFIG.get().theme.borders {
             padding.add_value(1);
         }
         padding
@@ -47,16 +47,16 @@ impl StyledWidget {
         TableStyle {
             table: Style::default(),
             header: Style::default()
-                .fg(CONFIG.theme.fg_header_color)
-                .bg(CONFIG.theme.bg_header_color),
+                .fg(CONFIG.get().theme.fg_header_color)
+                .bg(CONFIG.get().theme.bg_header_color),
             block: (Self::block(), Self::table_padding()),
             highlight: Style::default()
-                .fg(CONFIG.theme.fg_selected_color)
-                .bg(CONFIG.theme.bg_selected_color),
+                .fg(CONFIG.get().theme.fg_selected_color)
+                .bg(CONFIG.get().theme.bg_selected_color),
             normal: Style::default()
-                .fg(CONFIG.theme.fg_normal_color)
-                .bg(CONFIG.theme.bg_normal_color),
-            column_spacing: CONFIG.theme.column_spacing,
+                .fg(CONFIG.get().theme.fg_normal_color)
+                .bg(CONFIG.get().theme.bg_normal_color),
+            column_spacing: CONFIG.get().theme.column_spacing,
             col_highlight: Style::default(),
         }
     }
@@ -14,6 +14,7 @@ use rrss_core::globals::CONFIG;
 use rrss_core::models::Item;
 
 use crate::app::{AppRequest, ViewKind};
+use crate::keymaps::{Bindings, ViewKeyMap};
 use crate::theme::StyledWidget;
 use crate::widgets::scrollable_paragraph::ScrollableParagraph;
 
@@ -23,7 +24,7 @@ pub struct DetailedItemView<'a> {
     title: Paragraph<'a>,
     content: ScrollableParagraph<'a>,
     layout: Layout,
-    keymap: DetailedItemKeyMap,
+    keymap: Bindings<DetailedItemCommand>,
 }
 impl DetailedItemView<'_> {
     pub fn new(items: Vec<Item>, curr_idx: usize) -> Self {
@@ -35,7 +36,7 @@ impl DetailedItemView<'_> {
             layout: Layout::default()
                 .direction(Direction::Vertical)
                 .constraints(vec![Constraint::Length(1), Constraint::Fill(1)]),
-            keymap: KeyMap::default(),
+            keymap: Bindings::new::<DetailedItemKeyMap>(),
         };
         view.update_view();
         view
@@ -48,16 +49,17 @@ impl DetailedItemView<'_> {
         let mut sections = vec![];
 
         self.title = StyledWidget::header_paragraph(format!(
-            "({}/{}) - {}",
+            "({}/{}) {} {}",
             self.item_idx + 1,
             self.items.len(),
+            item.marker(),
             item.data.title.clone().unwrap_or_default()
         ));
         if let Some(title) = item.data.title {
             sections.push(format!("Title: {}", title));
         }
         if let Some(date) = item.data.posted {
-            let date = date.format(CONFIG.theme.date_format.as_str());
+            let date = date.format(&CONFIG.get().theme.date_format).to_string();
             sections.push(format!("Posted: {}", date));
         }
         if !sections.is_empty() {
@@ -82,7 +84,10 @@ impl View for DetailedItemView<'_> {
     fn title(&self) -> String {
         self.item().data.title.clone().unwrap_or_default()
     }
-    fn refresh(&mut self, _fm: &FeedManager) {
+    fn refresh(&mut self, fm: &FeedManager) {
+        if let Some(item) = fm.get_item(self.item().data.id.clone()) {
+            self.items[self.item_idx].state = item.state.clone();
+        }
         self.update_view();
     }
     fn update(&mut self, ev: &Event) -> AppRequest {
@@ -110,6 +115,12 @@ impl View for DetailedItemView<'_> {
                                 self.item().data.posted.unwrap_or_default(),
                             ))
                         }
+                        DetailedItemCommand::ToggleRead => {
+                            return AppRequest::ToggleItemRead(self.item().data.id.clone())
+                        }
+                        DetailedItemCommand::ToggleStarred => {
+                            return AppRequest::ToggleItemStarred(self.item().data.id.clone())
+                        }
                         DetailedItemCommand::PrevItem => {
                             self.item_idx = self.item_idx.saturating_sub(1).max(0);
                             return AppRequest::RefreshView;
@@ -138,6 +149,8 @@ pub enum DetailedItemCommand {
     OpenItem,
     OpenLinks,
     OpenItemInfo,
+    ToggleRead,
+    ToggleStarred,
     NextItem,
     PrevItem,
 }
@@ -147,6 +160,9 @@ impl Display for DetailedItemCommand {
     }
 }
 pub struct DetailedItemKeyMap(pub Vec<ShortCut<DetailedItemCommand>>);
+impl ViewKeyMap for DetailedItemKeyMap {
+    const VIEW: &'static str = "item";
+}
 impl KeyMap for DetailedItemKeyMap {
     type Command = DetailedItemCommand;
     fn get_shortcuts(&self) -> &[ShortCut<Self::Command>] {
@@ -166,6 +182,14 @@ impl KeyMap for DetailedItemKeyMap {
                 DetailedItemCommand::OpenItemInfo,
                 vec![KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE)],
             ),
+            ShortCut(
+                DetailedItemCommand::ToggleRead,
+                vec![KeyEvent::new(KeyCode::Char('m'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                DetailedItemCommand::ToggleStarred,
+                vec![KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE)],
+            ),
             ShortCut(
                 DetailedItemCommand::NextItem,
                 vec![
@@ -0,0 +1,102 @@
+use std::fmt::Display;
+
+use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::Rect;
+use ratatui::widgets::{Clear, TableState};
+use ratatui::Frame;
+use ratatui_helpers::keymap::{KeyMap, ShortCut};
+use ratatui_helpers::stateful_table::{IndexedRow, StatefulTable};
+use ratatui_helpers::view::View;
+use rrss_core::discovery::FeedCandidate;
+use rrss_core::feed_manager::FeedManager;
+
+use super::centered_rect;
+use crate::app::{AppRequest, ViewKind};
+use crate::keymaps::{Bindings, ViewKeyMap};
+use crate::theme::StyledWidget;
+
+pub struct DiscoveryView<'row> {
+    table: StatefulTable<'row, IndexedRow<FeedCandidate>>,
+    tags: Vec<String>,
+    keymap: Bindings<DiscoveryCommand>,
+}
+impl DiscoveryView<'_> {
+    pub fn new(candidates: Vec<FeedCandidate>, tags: Vec<String>) -> Self {
+        let title = format!("{} feeds found", candidates.len());
+        let table = StyledWidget::indexed_table(
+            candidates,
+            TableState::new().with_selected(0),
+            Some(title),
+        );
+        Self {
+            table,
+            tags,
+            keymap: Bindings::new::<DiscoveryKeyMap>(),
+        }
+    }
+}
+impl View for DiscoveryView<'_> {
+    type Model = FeedManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> Self::Kind {
+        ViewKind::Discovery
+    }
+    fn title(&self) -> String {
+        format!("rrss - discovery")
+    }
+    fn is_floating(&self) -> bool {
+        true
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        match ev {
+            Event::Key(ev) => match self.keymap.get_command(ev) {
+                Some(DiscoveryCommand::AddFeed) => {
+                    if let Some(id) = self.table.selected_value() {
+                        return AppRequest::CloseView
+                            + AppRequest::AddFeed(id.clone(), self.tags.clone());
+                    }
+                }
+                None => {}
+            },
+            _ => {}
+        }
+        AppRequest::None
+    }
+    fn compute_area(&self, area: Rect) -> Rect {
+        let (width, height) = self.table.min_area();
+        let (width, height) = (width.min(area.width), height.min(area.height));
+        centered_rect(area, (width, height.min(20)))
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        f.render_widget(Clear, area);
+        self.table.draw(f, area)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum DiscoveryCommand {
+    AddFeed,
+}
+impl Display for DiscoveryCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+pub struct DiscoveryKeyMap(pub Vec<ShortCut<DiscoveryCommand>>);
+impl ViewKeyMap for DiscoveryKeyMap {
+    const VIEW: &'static str = "discovery";
+}
+impl KeyMap for DiscoveryKeyMap {
+    type Command = DiscoveryCommand;
+    fn get_shortcuts(&self) -> &[ShortCut<Self::Command>] {
+        &self.0
+    }
+    fn default() -> Self {
+        Self(vec![ShortCut(
+            DiscoveryCommand::AddFeed,
+            vec![KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)],
+        )])
+    }
+}
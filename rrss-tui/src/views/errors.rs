@@ -0,0 +1,108 @@
+use std::fmt::Display;
+
+use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::Rect;
+use ratatui::widgets::{Clear, TableState};
+use ratatui::Frame;
+use ratatui_helpers::keymap::{KeyMap, ShortCut};
+use ratatui_helpers::stateful_table::{IndexedRow, StatefulTable};
+use ratatui_helpers::view::View;
+use rrss_core::error::FetchError;
+use rrss_core::feed_manager::FeedManager;
+
+use super::centered_rect;
+use crate::app::{AppRequest, ViewKind};
+use crate::keymaps::{Bindings, ViewKeyMap};
+use crate::theme::StyledWidget;
+
+pub struct ErrorsView<'row> {
+    table: StatefulTable<'row, IndexedRow<FetchError>>,
+    keymap: Bindings<ErrorsCommand>,
+}
+impl ErrorsView<'_> {
+    pub fn new(errors: Vec<FetchError>) -> Self {
+        let title = format!("{} feeds failed to update", errors.len());
+        let table =
+            StyledWidget::indexed_table(errors, TableState::new().with_selected(0), Some(title));
+        Self {
+            table,
+            keymap: Bindings::new::<ErrorsKeyMap>(),
+        }
+    }
+}
+impl View for ErrorsView<'_> {
+    type Model = FeedManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> Self::Kind {
+        ViewKind::Errors
+    }
+    fn title(&self) -> String {
+        format!("rrss - errors")
+    }
+    fn is_floating(&self) -> bool {
+        true
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        match ev {
+            Event::Key(ev) => match self.keymap.get_command(ev) {
+                Some(ErrorsCommand::UpdateFeed) => {
+                    if let Some(id) = self.table.selected_value() {
+                        return AppRequest::UpdateFeed(id.clone());
+                    }
+                }
+                Some(ErrorsCommand::ViewFeedInfo) => {
+                    if let Some(id) = self.table.selected_value() {
+                        return AppRequest::OpenInfoFeedView(id.clone());
+                    }
+                }
+                None => {}
+            },
+            _ => {}
+        }
+        AppRequest::None
+    }
+    fn compute_area(&self, area: Rect) -> Rect {
+        let (width, height) = self.table.min_area();
+        let (width, height) = (width.min(area.width), height.min(area.height));
+        centered_rect(area, (width, height.min(20)))
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        f.render_widget(Clear, area);
+        self.table.draw(f, area)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ErrorsCommand {
+    UpdateFeed,
+    ViewFeedInfo,
+}
+impl Display for ErrorsCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+pub struct ErrorsKeyMap(pub Vec<ShortCut<ErrorsCommand>>);
+impl ViewKeyMap for ErrorsKeyMap {
+    const VIEW: &'static str = "errors";
+}
+impl KeyMap for ErrorsKeyMap {
+    type Command = ErrorsCommand;
+    fn get_shortcuts(&self) -> &[ShortCut<Self::Command>] {
+        &self.0
+    }
+    fn default() -> Self {
+        Self(vec![
+            ShortCut(
+                ErrorsCommand::UpdateFeed,
+                vec![KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                ErrorsCommand::ViewFeedInfo,
+                vec![KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE)],
+            ),
+        ])
+    }
+}
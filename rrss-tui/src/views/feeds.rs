@@ -9,18 +9,21 @@ use ratatui_helpers::keymap::{KeyMap, ShortCut};
 use ratatui_helpers::stateful_table::{IndexedRow, StatefulTable};
 use ratatui_helpers::view::View;
 use rrss_core::feed_manager::FeedManager;
-use rrss_core::filter::Filter;
-use rrss_core::models::{Feed, Item, Tag};
+use rrss_core::filter::{Expr, Filter};
+use rrss_core::models::{Feed, FeedId, FeedRef, FeedRow, Item, Tag};
 use rrss_core::sorter::Sorter;
 
+use super::prompt::PromptKind;
 use crate::app::{AppRequest, ViewKind};
+use crate::keymaps::{Bindings, ViewKeyMap};
 use crate::theme::StyledWidget;
 
 pub struct FeedsView<'row> {
-    table: StatefulTable<'row, IndexedRow<Feed>>,
+    table: StatefulTable<'row, IndexedRow<FeedRow>>,
     filter: Filter,
+    base: Filter,
     sorter: Sorter<Feed>,
-    keymap: FeedsKeyMap,
+    keymap: Bindings<FeedsCommand>,
 }
 impl FeedsView<'_> {
     pub fn new(
@@ -32,12 +35,26 @@ impl FeedsView<'_> {
         if state.selected().is_none() {
             state.select(Some(0));
         }
-        let table = StyledWidget::indexed_table(fm.get_feeds(&filter, &sorter), state, None);
+        let table = StyledWidget::indexed_table(fm.get_feed_rows(&filter, &sorter), state, None);
         FeedsView {
             table,
+            base: filter.clone(),
             filter,
             sorter,
-            keymap: KeyMap::default(),
+            keymap: Bindings::new::<FeedsKeyMap>(),
+        }
+    }
+    fn selected_feed(&self) -> Option<&FeedId> {
+        match self.table.selected_value() {
+            Some(FeedRef::Feed(id)) => Some(id),
+            _ => None,
+        }
+    }
+    fn open_selected(&self) -> AppRequest {
+        match self.table.selected_value() {
+            Some(FeedRef::Feed(id)) => AppRequest::OpenItemsView(id.clone(), Item::BY_POSTED_REV),
+            Some(FeedRef::Virtual(name)) => AppRequest::OpenVirtualFeedView(name.clone()),
+            None => AppRequest::None,
         }
     }
 }
@@ -52,12 +69,14 @@ impl View for FeedsView<'_> {
         format!("rrss - feeds")
     }
     fn refresh(&mut self, fm: &FeedManager) {
+        let base = self.base.clone();
         *self = Self::new(
             fm,
             self.filter.clone(),
             self.sorter.clone(),
             self.table.state().clone(),
         );
+        self.base = base;
     }
     fn update(&mut self, ev: &Event) -> AppRequest {
         self.table.update(ev);
@@ -69,7 +88,7 @@ impl View for FeedsView<'_> {
                             return AppRequest::UpdateFeeds(self.filter.clone())
                         }
                         FeedsCommand::UpdateFeed => {
-                            if let Some(id) = self.table.selected_value() {
+                            if let Some(id) = self.selected_feed() {
                                 return AppRequest::UpdateFeed(id.clone());
                             }
                         }
@@ -77,34 +96,67 @@ impl View for FeedsView<'_> {
                             return AppRequest::OpenTagView(Filter::new(), Tag::BY_NAME)
                         }
                         FeedsCommand::ClearFilters => {
-                            self.filter = Filter::new();
+                            self.filter = self.base.clone();
                             return AppRequest::RefreshView;
                         }
-                        FeedsCommand::MarkFeedAsRead => {
-                            if let Some(id) = self.table.selected_value() {
-                                return AppRequest::MarkFeedAsRead(id.clone());
+                        FeedsCommand::MarkFeedAsRead => match self.table.selected_value() {
+                            Some(FeedRef::Feed(id)) => {
+                                return AppRequest::MarkFeedAsRead(id.clone())
                             }
-                        }
+                            Some(FeedRef::Virtual(name)) => {
+                                return AppRequest::MarkVirtualFeedAsRead(name.clone())
+                            }
+                            None => {}
+                        },
                         FeedsCommand::ViewFeedLinks => {
-                            if let Some(id) = self.table.selected_value() {
+                            if let Some(id) = self.selected_feed() {
                                 return AppRequest::OpenLinksView(
                                     Filter::new().feed_id(id.clone()),
                                 );
                             }
                         }
                         FeedsCommand::ViewFeedInfo => {
-                            if let Some(id) = self.table.selected_value() {
+                            if let Some(id) = self.selected_feed() {
                                 return AppRequest::OpenInfoFeedView(id.clone());
                             }
                         }
-                        FeedsCommand::OpenFeed => {
-                            if let Some(id) = self.table.selected_value() {
-                                return AppRequest::OpenItemsView(id.clone(), Item::BY_POSTED_REV);
+                        FeedsCommand::OpenFeed => return self.open_selected(),
+                        FeedsCommand::ClearFeed => {
+                            if let Some(id) = self.selected_feed() {
+                                return AppRequest::OpenPromptDock(PromptKind::ClearFeed(
+                                    id.clone(),
+                                ));
                             }
                         }
-                        FeedsCommand::ClearFeed => {
-                            if let Some(id) = self.table.selected_value() {
-                                return AppRequest::ClearFeed(id.clone());
+                        FeedsCommand::ViewHealth => return AppRequest::OpenHealthView,
+                        FeedsCommand::ViewTimeline => {
+                            let mut filter = Filter::new().unread_item();
+                            filter.tag_id = self.filter.tag_id.clone();
+                            return AppRequest::OpenTimelineView(filter);
+                        }
+                        FeedsCommand::ViewStarred => {
+                            return AppRequest::OpenTimelineView(Filter::new().starred())
+                        }
+                        FeedsCommand::AddFeed => {
+                            return AppRequest::OpenPromptDock(PromptKind::AddFeed)
+                        }
+                        FeedsCommand::RemoveFeed => {
+                            if let Some(id) = self.selected_feed() {
+                                return AppRequest::OpenPromptDock(PromptKind::RemoveFeed(
+                                    id.clone(),
+                                ));
+                            }
+                        }
+                        FeedsCommand::TagFeed => {
+                            if let Some(id) = self.selected_feed() {
+                                return AppRequest::OpenPromptDock(PromptKind::TagFeed(id.clone()));
+                            }
+                        }
+                        FeedsCommand::EditMaxItems => {
+                            if let Some(id) = self.selected_feed() {
+                                return AppRequest::OpenPromptDock(PromptKind::SetMaxItems(
+                                    id.clone(),
+                                ));
                             }
                         }
                     }
@@ -120,9 +172,8 @@ impl View for FeedsView<'_> {
                         if let Some(row) = self.table.screen_coords_to_row_index(pos)
                             && let Some(idx) = self.table.selected_row()
                             && row == idx
-                            && let Some(id) = self.table.selected_value()
                         {
-                            return AppRequest::OpenItemsView(id.clone(), Item::BY_POSTED_REV);
+                            return self.open_selected();
                         }
                     }
                     _ => {}
@@ -136,13 +187,15 @@ impl View for FeedsView<'_> {
         self.table.draw(f, area);
     }
     fn on_prompt_submit(&mut self, _value: String) -> AppRequest {
-        if let Some(id) = self.table.selected_value() {
-            return AppRequest::OpenItemsView(id.clone(), Item::BY_POSTED_REV);
-        }
-        AppRequest::None
+        self.open_selected()
     }
     fn on_prompt_change(&mut self, value: String) -> AppRequest {
-        self.filter.feed_contains = Some(value);
+        // an unfinished query keeps the last one that parsed
+        if value.trim().is_empty() {
+            self.filter = self.base.clone();
+        } else if let Ok(expr) = Expr::parse(&value) {
+            self.filter = self.base.clone().and_query(expr);
+        }
         AppRequest::RefreshView
     }
 }
@@ -158,6 +211,13 @@ pub enum FeedsCommand {
     ViewFeedInfo,
     OpenFeed,
     ClearFeed,
+    ViewHealth,
+    ViewTimeline,
+    ViewStarred,
+    AddFeed,
+    RemoveFeed,
+    TagFeed,
+    EditMaxItems,
 }
 impl Display for FeedsCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -165,6 +225,9 @@ impl Display for FeedsCommand {
     }
 }
 pub struct FeedsKeyMap(pub Vec<ShortCut<FeedsCommand>>);
+impl ViewKeyMap for FeedsKeyMap {
+    const VIEW: &'static str = "feeds";
+}
 impl KeyMap for FeedsKeyMap {
     type Command = FeedsCommand;
     fn get_shortcuts(&self) -> &[ShortCut<Self::Command>] {
@@ -208,6 +271,34 @@ impl KeyMap for FeedsKeyMap {
                 FeedsCommand::ClearFeed,
                 vec![KeyEvent::new(KeyCode::Char('c'), KeyModifiers::SHIFT)],
             ),
+            ShortCut(
+                FeedsCommand::ViewHealth,
+                vec![KeyEvent::new(KeyCode::Char('e'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                FeedsCommand::AddFeed,
+                vec![KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                FeedsCommand::RemoveFeed,
+                vec![KeyEvent::new(KeyCode::Char('d'), KeyModifiers::SHIFT)],
+            ),
+            ShortCut(
+                FeedsCommand::TagFeed,
+                vec![KeyEvent::new(KeyCode::Char('t'), KeyModifiers::SHIFT)],
+            ),
+            ShortCut(
+                FeedsCommand::ViewTimeline,
+                vec![KeyEvent::new(KeyCode::Char('u'), KeyModifiers::SHIFT)],
+            ),
+            ShortCut(
+                FeedsCommand::ViewStarred,
+                vec![KeyEvent::new(KeyCode::Char('s'), KeyModifiers::SHIFT)],
+            ),
+            ShortCut(
+                FeedsCommand::EditMaxItems,
+                vec![KeyEvent::new(KeyCode::Char('m'), KeyModifiers::NONE)],
+            ),
         ]))
     }
 }
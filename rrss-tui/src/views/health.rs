@@ -0,0 +1,132 @@
+use std::fmt::Display;
+
+use ratatui::crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEventKind,
+};
+use ratatui::layout::{Position, Rect};
+use ratatui::widgets::TableState;
+use ratatui::Frame;
+use ratatui_helpers::keymap::{KeyMap, ShortCut};
+use ratatui_helpers::stateful_table::{IndexedRow, StatefulTable};
+use ratatui_helpers::view::View;
+use rrss_core::feed_manager::FeedManager;
+use rrss_core::filter::Filter;
+use rrss_core::models::{Feed, FeedHealth, Item};
+
+use crate::app::{AppRequest, ViewKind};
+use crate::keymaps::{Bindings, ViewKeyMap};
+use crate::theme::StyledWidget;
+
+pub struct HealthView<'row> {
+    table: StatefulTable<'row, IndexedRow<FeedHealth>>,
+    keymap: Bindings<HealthCommand>,
+}
+impl HealthView<'_> {
+    pub fn new(fm: &FeedManager, mut state: TableState) -> Self {
+        if state.selected().is_none() {
+            state.select(Some(0));
+        }
+        let feeds = fm.get_health(&Filter::new().unhealthy(), &Feed::BY_FAILURES_REV);
+        let title = format!("{} unhealthy feeds", feeds.len());
+        Self {
+            table: StyledWidget::indexed_table(feeds, state, Some(title)),
+            keymap: Bindings::new::<HealthKeyMap>(),
+        }
+    }
+}
+impl View for HealthView<'_> {
+    type Model = FeedManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> Self::Kind {
+        ViewKind::Health
+    }
+    fn title(&self) -> String {
+        format!("rrss - health")
+    }
+    fn refresh(&mut self, fm: &FeedManager) {
+        *self = Self::new(fm, self.table.state().clone());
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        match ev {
+            Event::Key(ev) => match self.keymap.get_command(ev) {
+                Some(HealthCommand::UpdateFeed) => {
+                    if let Some(id) = self.table.selected_value() {
+                        return AppRequest::UpdateFeed(id.clone());
+                    }
+                }
+                Some(HealthCommand::ViewFeedInfo) => {
+                    if let Some(id) = self.table.selected_value() {
+                        return AppRequest::OpenInfoFeedView(id.clone());
+                    }
+                }
+                Some(HealthCommand::OpenFeed) => {
+                    if let Some(id) = self.table.selected_value() {
+                        return AppRequest::OpenItemsView(id.clone(), Item::BY_POSTED_REV);
+                    }
+                }
+                None => {}
+            },
+            Event::Mouse(ev) => match ev.kind {
+                MouseEventKind::Up(MouseButton::Left) => {
+                    let pos = Position {
+                        x: ev.column,
+                        y: ev.row,
+                    };
+                    if let Some(row) = self.table.screen_coords_to_row_index(pos)
+                        && let Some(idx) = self.table.selected_row()
+                        && row == idx
+                        && let Some(id) = self.table.selected_value()
+                    {
+                        return AppRequest::OpenItemsView(id.clone(), Item::BY_POSTED_REV);
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        AppRequest::None
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        self.table.draw(f, area)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum HealthCommand {
+    UpdateFeed,
+    ViewFeedInfo,
+    OpenFeed,
+}
+impl Display for HealthCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+pub struct HealthKeyMap(pub Vec<ShortCut<HealthCommand>>);
+impl ViewKeyMap for HealthKeyMap {
+    const VIEW: &'static str = "health";
+}
+impl KeyMap for HealthKeyMap {
+    type Command = HealthCommand;
+    fn get_shortcuts(&self) -> &[ShortCut<Self::Command>] {
+        &self.0
+    }
+    fn default() -> Self {
+        Self(vec![
+            ShortCut(
+                HealthCommand::UpdateFeed,
+                vec![KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                HealthCommand::ViewFeedInfo,
+                vec![KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                HealthCommand::OpenFeed,
+                vec![KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)],
+            ),
+        ])
+    }
+}
@@ -1,49 +1,97 @@
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
 use ratatui::crossterm::event::Event;
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::layout::{Constraint, Rect};
 use ratatui::widgets::TableState;
 use ratatui::Frame;
-use ratatui_helpers::keymap::{KeyMap, ShortCut};
-use ratatui_helpers::stateful_table::StatefulTable;
+use ratatui_helpers::stateful_table::{StatefulTable, Tabular};
 use ratatui_helpers::view::View;
 use rrss_core::feed_manager::FeedManager;
 
-use super::feeds::{FeedsCommand, FeedsKeyMap};
-use super::items::{ItemsCommand, ItemsKeyMap};
+use super::detailed_item::DetailedItemKeyMap;
+use super::discovery::DiscoveryKeyMap;
+use super::errors::ErrorsKeyMap;
+use super::feeds::FeedsKeyMap;
+use super::health::HealthKeyMap;
+use super::items::ItemsKeyMap;
+use super::links::LinksKeyMap;
+use super::tags::TagsKeyMap;
 use crate::app::{AppRequest, ViewKind};
-use crate::keymaps::{AppCommand, AppKeyMap};
+use crate::keymaps::{AppKeyMap, Bindings, ViewKeyMap};
 use crate::theme::StyledWidget;
+use crate::widgets::scrollable_paragraph::ScrollableParagraphKeyMap;
+
+pub struct KeybindRow {
+    view: &'static str,
+    command: String,
+    keys: String,
+}
+impl KeybindRow {
+    fn rows<K: ViewKeyMap>() -> Vec<Self>
+    where
+        K::Command: Debug,
+    {
+        Bindings::new::<K>()
+            .rows()
+            .into_iter()
+            .map(|(command, keys)| Self {
+                view: K::VIEW,
+                command,
+                keys,
+            })
+            .collect()
+    }
+}
+impl Tabular for KeybindRow {
+    type Value = ();
+    fn cmp_by_col(&self, other: &Self, col: usize) -> Ordering {
+        match col {
+            0 => self.view.cmp(other.view),
+            1 => self.command.cmp(&other.command),
+            2 => self.keys.cmp(&other.keys),
+            _ => panic!(),
+        }
+    }
+    fn value(&self) -> Self::Value {}
+    fn content(&self) -> Vec<String> {
+        vec![
+            self.view.to_string(),
+            self.command.clone(),
+            self.keys.clone(),
+        ]
+    }
+    fn column_names() -> Option<Vec<String>> {
+        Some(vec![format!("View"), format!("Command"), format!("Keys")])
+    }
+    fn column_constraints() -> Vec<fn(u16) -> Constraint> {
+        vec![Constraint::Length, Constraint::Length, Constraint::Fill]
+    }
+}
 
 pub struct HelpView<'a> {
-    app_table: StatefulTable<'a, ShortCut<AppCommand>>,
-    feeds_table: StatefulTable<'a, ShortCut<FeedsCommand>>,
-    items_table: StatefulTable<'a, ShortCut<ItemsCommand>>,
-    layout: Layout,
+    table: StatefulTable<'a, KeybindRow>,
 }
 impl Default for HelpView<'_> {
     fn default() -> Self {
+        let rows = [
+            KeybindRow::rows::<AppKeyMap>(),
+            KeybindRow::rows::<FeedsKeyMap>(),
+            KeybindRow::rows::<ItemsKeyMap>(),
+            KeybindRow::rows::<DetailedItemKeyMap>(),
+            KeybindRow::rows::<ScrollableParagraphKeyMap>(),
+            KeybindRow::rows::<TagsKeyMap>(),
+            KeybindRow::rows::<LinksKeyMap>(),
+            KeybindRow::rows::<ErrorsKeyMap>(),
+            KeybindRow::rows::<HealthKeyMap>(),
+            KeybindRow::rows::<DiscoveryKeyMap>(),
+        ];
         Self {
-            app_table: StyledWidget::table(
-                AppKeyMap::default().0,
-                TableState::default(),
-                Some("Global Shortcuts".into()),
-            ),
-            feeds_table: StyledWidget::table(
-                FeedsKeyMap::default().0,
-                TableState::default(),
-                Some("Feeds Shortcuts".into()),
-            ),
-            items_table: StyledWidget::table(
-                ItemsKeyMap::default().0,
+            table: StyledWidget::table(
+                rows.into_iter().flatten().collect(),
                 TableState::default(),
-                Some("items Shortcuts".into()),
+                Some("Shortcuts".into()),
             ),
-            layout: Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints(vec![
-                    Constraint::Fill(1),
-                    Constraint::Fill(1),
-                    Constraint::Fill(1),
-                ]),
         }
     }
 }
@@ -56,16 +104,10 @@ impl View for HelpView<'_> {
         ViewKind::Help
     }
     fn update(&mut self, ev: &Event) -> Self::Signal {
-        // todo: only update focused tables
-        self.app_table.update(ev);
-        self.feeds_table.update(ev);
-        self.items_table.update(ev);
+        self.table.update(ev);
         Self::Signal::default()
     }
     fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
-        let layout = self.layout.split(area);
-        self.app_table.draw(f, layout[0]);
-        self.feeds_table.draw(f, layout[1]);
-        self.items_table.draw(f, layout[2]);
+        self.table.draw(f, area);
     }
 }
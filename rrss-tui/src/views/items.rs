@@ -9,18 +9,20 @@ use ratatui_helpers::keymap::{KeyMap, ShortCut};
 use ratatui_helpers::stateful_table::{IndexedRow, StatefulTable};
 use ratatui_helpers::view::View;
 use rrss_core::feed_manager::FeedManager;
-use rrss_core::filter::Filter;
+use rrss_core::filter::{Expr, Filter};
 use rrss_core::models::Item;
 use rrss_core::sorter::Sorter;
 
 use crate::app::{AppRequest, ViewKind};
+use crate::keymaps::{Bindings, ViewKeyMap};
 use crate::theme::StyledWidget;
 
 pub struct ItemsView<'row> {
     table: StatefulTable<'row, IndexedRow<Item>>,
+    base: Filter,
     filter: Filter,
     sorter: Sorter<Item>,
-    keymap: ItemsKeyMap,
+    keymap: Bindings<ItemsCommand>,
 }
 impl ItemsView<'_> {
     pub fn new(
@@ -32,11 +34,13 @@ impl ItemsView<'_> {
         if state.selected().is_none() {
             state.select(Some(0));
         }
+        let filter = filter.visible();
         ItemsView {
             table: StyledWidget::indexed_table(fm.get_items(&filter, &sorter), state, None),
+            base: filter.clone(),
             filter,
             sorter,
-            keymap: KeyMap::default(),
+            keymap: Bindings::new::<ItemsKeyMap>(),
         }
     }
 }
@@ -51,12 +55,14 @@ impl View for ItemsView<'_> {
         format!("rrss - items")
     }
     fn refresh(&mut self, fm: &FeedManager) {
+        let base = self.base.clone();
         *self = Self::new(
             fm,
             self.filter.clone(),
             self.sorter.clone(),
             self.table.state().clone(),
         );
+        self.base = base;
     }
     fn update(&mut self, ev: &Event) -> AppRequest {
         self.table.update(ev);
@@ -84,8 +90,7 @@ impl View for ItemsView<'_> {
                             }
                         }
                         ItemsCommand::ClearFilters => {
-                            self.filter =
-                                Filter::new().feed_id(self.filter.feed_id.clone().unwrap());
+                            self.filter = self.base.clone();
                             return AppRequest::RefreshView;
                         }
                         ItemsCommand::MarkItemAsRead => {
@@ -93,6 +98,16 @@ impl View for ItemsView<'_> {
                                 return AppRequest::MarkItemAsRead(id.clone());
                             }
                         }
+                        ItemsCommand::ToggleRead => {
+                            if let Some(id) = self.table.selected_value() {
+                                return AppRequest::ToggleItemRead(id.clone());
+                            }
+                        }
+                        ItemsCommand::ToggleStarred => {
+                            if let Some(id) = self.table.selected_value() {
+                                return AppRequest::ToggleItemStarred(id.clone());
+                            }
+                        }
                         ItemsCommand::ViewItemInfo => {
                             if let Some(id) = self.table.selected_value() {
                                 return AppRequest::OpenInfoItemView(id.clone());
@@ -157,7 +172,12 @@ impl View for ItemsView<'_> {
         AppRequest::None
     }
     fn on_prompt_change(&mut self, value: String) -> AppRequest {
-        self.filter.item_contains = Some(value);
+        // an unfinished query keeps the last one that parsed
+        if value.trim().is_empty() {
+            self.filter = self.base.clone();
+        } else if let Ok(expr) = Expr::parse(&value) {
+            self.filter = self.base.clone().and_query(expr);
+        }
         AppRequest::RefreshView
     }
 }
@@ -169,6 +189,8 @@ pub enum ItemsCommand {
     UpdateFeed,
     ClearFilters,
     MarkItemAsRead,
+    ToggleRead,
+    ToggleStarred,
     ViewItemInfo,
     ViewItemLinks,
 }
@@ -178,6 +200,9 @@ impl Display for ItemsCommand {
     }
 }
 pub struct ItemsKeyMap(pub Vec<ShortCut<ItemsCommand>>);
+impl ViewKeyMap for ItemsKeyMap {
+    const VIEW: &'static str = "items";
+}
 impl KeyMap for ItemsKeyMap {
     type Command = ItemsCommand;
     fn get_shortcuts(&self) -> &[ShortCut<Self::Command>] {
@@ -201,6 +226,14 @@ impl KeyMap for ItemsKeyMap {
                 ItemsCommand::MarkItemAsRead,
                 vec![KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE)],
             ),
+            ShortCut(
+                ItemsCommand::ToggleRead,
+                vec![KeyEvent::new(KeyCode::Char('m'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                ItemsCommand::ToggleStarred,
+                vec![KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE)],
+            ),
             ShortCut(
                 ItemsCommand::ViewItemInfo,
                 vec![KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE)],
@@ -1,22 +1,31 @@
+use std::fmt::Display;
+
+use crossterm::event::{KeyEvent, KeyModifiers};
 use ratatui::crossterm::event::{Event, KeyCode, MouseButton, MouseEventKind};
 use ratatui::layout::{Position, Rect};
 use ratatui::widgets::{Clear, TableState};
 use ratatui::Frame;
+use ratatui_helpers::keymap::{KeyMap, ShortCut};
 use ratatui_helpers::stateful_table::{IndexedRow, StatefulTable};
 use ratatui_helpers::view::View;
 use rrss_core::feed_manager::FeedManager;
 use rrss_core::models::Link;
 
 use crate::app::{AppRequest, ViewKind};
+use crate::keymaps::{Bindings, ViewKeyMap};
 use crate::theme::StyledWidget;
 
 pub struct LinksView<'row> {
     table: StatefulTable<'row, IndexedRow<Link>>,
+    keymap: Bindings<LinksCommand>,
 }
 impl LinksView<'_> {
     pub fn new(links: Vec<Link>) -> Self {
         let table = StyledWidget::indexed_table(links, TableState::new().with_selected(0), None);
-        Self { table }
+        Self {
+            table,
+            keymap: Bindings::new::<LinksKeyMap>(),
+        }
     }
 }
 impl View for LinksView<'_> {
@@ -35,14 +44,14 @@ impl View for LinksView<'_> {
     fn update(&mut self, ev: &Event) -> AppRequest {
         self.table.update(ev);
         match ev {
-            Event::Key(ev) => match ev.code {
-                KeyCode::Enter | KeyCode::Char('o') => {
+            Event::Key(ev) => match self.keymap.get_command(ev) {
+                Some(LinksCommand::OpenLink) => {
                     if let Some(id) = self.table.selected_value() {
                         let _ = open::that_detached(id);
                         return AppRequest::CloseView;
                     }
                 }
-                _ => {}
+                None => {}
             },
             Event::Mouse(ev) => {
                 let pos = Position {
@@ -72,3 +81,32 @@ impl View for LinksView<'_> {
         self.table.draw(f, area)
     }
 }
+
+#[derive(Debug, Clone)]
+pub enum LinksCommand {
+    OpenLink,
+}
+impl Display for LinksCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+pub struct LinksKeyMap(pub Vec<ShortCut<LinksCommand>>);
+impl ViewKeyMap for LinksKeyMap {
+    const VIEW: &'static str = "links";
+}
+impl KeyMap for LinksKeyMap {
+    type Command = LinksCommand;
+    fn get_shortcuts(&self) -> &[ShortCut<Self::Command>] {
+        &self.0
+    }
+    fn default() -> Self {
+        Self(Vec::from([ShortCut(
+            LinksCommand::OpenLink,
+            vec![
+                KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+                KeyEvent::new(KeyCode::Char('o'), KeyModifiers::NONE),
+            ],
+        )]))
+    }
+}
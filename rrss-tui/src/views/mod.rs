@@ -1,13 +1,18 @@
 use ratatui::layout::Rect;
 
 pub mod detailed_item;
+pub mod discovery;
+pub mod errors;
 pub mod feeds;
+pub mod health;
 pub mod help;
 pub mod items;
 pub mod links;
 pub mod popup;
 pub mod prompt;
+pub mod search;
 pub mod tags;
+pub mod timeline;
 
 pub fn centered_rect(area: Rect, (width, height): (u16, u16)) -> Rect {
     Rect {
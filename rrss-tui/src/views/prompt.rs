@@ -4,20 +4,51 @@ use ratatui::widgets::Paragraph;
 use ratatui::Frame;
 use ratatui_helpers::view::View;
 use rrss_core::feed_manager::FeedManager;
+use rrss_core::filter::Expr;
+use rrss_core::models::FeedId;
 
 use crate::app::{AppRequest, ViewKind};
 use crate::widgets::tui_input::{to_input_request, Input, StateChanged};
 
+#[derive(Clone)]
+pub enum PromptKind {
+    Search,
+    SearchAll,
+    AddFeed,
+    RemoveFeed(FeedId),
+    ClearFeed(FeedId),
+    TagFeed(FeedId),
+    SetMaxItems(FeedId),
+}
+impl PromptKind {
+    fn is_search(&self) -> bool {
+        matches!(self, PromptKind::Search | PromptKind::SearchAll)
+    }
+    fn prefix(&self) -> String {
+        match self {
+            PromptKind::Search => format!("Filter: "),
+            PromptKind::SearchAll => format!("Search all items: "),
+            PromptKind::AddFeed => format!("Add feed (url [tags]): "),
+            PromptKind::RemoveFeed(id) => format!("Remove {}? (y/n): ", id.0),
+            PromptKind::ClearFeed(id) => format!("Clear {}? (y/n): ", id.0),
+            PromptKind::TagFeed(id) => format!("Tag {} with: ", id.0),
+            PromptKind::SetMaxItems(id) => format!("Max items for {}: ", id.0),
+        }
+    }
+}
+
 pub struct PromptView {
-    prefix: String,
+    kind: PromptKind,
     input: Input,
+    error: Option<String>,
 }
 
 impl PromptView {
-    pub fn new(prefix: String) -> Self {
+    pub fn new(kind: PromptKind) -> Self {
         Self {
-            prefix,
+            kind,
             input: Input::default(),
+            error: None,
         }
     }
 }
@@ -32,26 +63,51 @@ impl View for PromptView {
         match ev {
             Event::Key(key) => match key.code {
                 KeyCode::Esc => {
-                    return AppRequest::ChangePromptValue("".to_string())
-                        + AppRequest::CloseSearchDock
+                    return match self.kind {
+                        PromptKind::Search | PromptKind::SearchAll => {
+                            AppRequest::ChangePromptValue("".to_string())
+                                + AppRequest::ClosePromptDock
+                        }
+                        _ => AppRequest::ClosePromptDock,
+                    }
                 }
                 KeyCode::Enter => {
-                    return AppRequest::CloseSearchDock
-                        + AppRequest::SubmitPromptValue(self.input.value().to_string())
+                    let value = self.input.value().to_string();
+                    return match self.kind {
+                        PromptKind::Search | PromptKind::SearchAll => {
+                            AppRequest::ClosePromptDock + AppRequest::SubmitPromptValue(value)
+                        }
+                        _ => {
+                            AppRequest::ClosePromptDock
+                                + AppRequest::SubmitPromptAction(self.kind.clone(), value)
+                        }
+                    };
                 }
                 _ => {}
             },
             _ => {}
         }
         if let Some(req) = to_input_request(ev) {
-            if let Some(StateChanged { value: true, .. }) = self.input.handle(req) {
-                return AppRequest::ChangePromptValue(self.input.value().to_string());
+            if let Some(StateChanged { value: true, .. }) = self.input.handle(req)
+                && self.kind.is_search()
+            {
+                let value = self.input.value().to_string();
+                self.error = match self.kind {
+                    PromptKind::Search if !value.trim().is_empty() => {
+                        Expr::parse(&value).err().map(|e| e.to_string())
+                    }
+                    _ => None,
+                };
+                return AppRequest::ChangePromptValue(value);
             }
         }
         AppRequest::None
     }
     fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
-        let p = Paragraph::new(self.prefix.clone() + self.input.value());
-        f.render_widget(p, area)
+        let mut line = self.kind.prefix() + self.input.value();
+        if let Some(error) = &self.error {
+            line.push_str(&format!("  [{error}]"));
+        }
+        f.render_widget(Paragraph::new(line), area)
     }
 }
@@ -0,0 +1,95 @@
+use ratatui::crossterm::event::Event;
+use ratatui::layout::Rect;
+use ratatui::widgets::TableState;
+use ratatui::Frame;
+use ratatui_helpers::stateful_table::{IndexedRow, StatefulTable};
+use ratatui_helpers::view::View;
+use rrss_core::feed_manager::FeedManager;
+use rrss_core::filter::Filter;
+use rrss_core::search::SearchHit;
+use rrss_core::sorter::Sorter;
+
+use super::items::{ItemsCommand, ItemsKeyMap};
+use crate::app::{AppRequest, ViewKind};
+use crate::keymaps::Bindings;
+use crate::theme::StyledWidget;
+
+pub struct SearchView<'row> {
+    table: StatefulTable<'row, IndexedRow<SearchHit>>,
+    query: String,
+    keymap: Bindings<ItemsCommand>,
+}
+impl SearchView<'_> {
+    pub fn new(fm: &FeedManager, query: String, mut state: TableState) -> Self {
+        if state.selected().is_none() {
+            state.select(Some(0));
+        }
+        let hits = fm.search(&query);
+        let title = format!("{} results for: {}", hits.len(), query);
+        Self {
+            table: StyledWidget::indexed_table(hits, state, Some(title)),
+            query,
+            keymap: Bindings::new::<ItemsKeyMap>(),
+        }
+    }
+    fn view_selected(&self) -> AppRequest {
+        if let Some(id) = self.table.selected_value() {
+            return AppRequest::OpenDetailedItemView(
+                Filter::new().item_id(id.clone()),
+                Sorter::NONE,
+                0,
+            );
+        }
+        AppRequest::None
+    }
+}
+impl View for SearchView<'_> {
+    type Model = FeedManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> Self::Kind {
+        ViewKind::Search
+    }
+    fn title(&self) -> String {
+        format!("rrss - search")
+    }
+    fn refresh(&mut self, fm: &FeedManager) {
+        *self = Self::new(fm, self.query.clone(), self.table.state().clone());
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        match ev {
+            Event::Key(ev) => match self.keymap.get_command(ev) {
+                Some(ItemsCommand::ViewItem) => return self.view_selected(),
+                Some(ItemsCommand::OpenItem) => {
+                    if let Some(id) = self.table.selected_value() {
+                        return AppRequest::OpenItem(id.clone());
+                    }
+                }
+                Some(ItemsCommand::MarkItemAsRead) => {
+                    if let Some(id) = self.table.selected_value() {
+                        return AppRequest::MarkItemAsRead(id.clone());
+                    }
+                }
+                Some(ItemsCommand::ViewItemInfo) => {
+                    if let Some(id) = self.table.selected_value() {
+                        return AppRequest::OpenInfoItemView(id.clone());
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        AppRequest::None
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        self.table.draw(f, area)
+    }
+    fn on_prompt_submit(&mut self, _value: String) -> AppRequest {
+        self.view_selected()
+    }
+    fn on_prompt_change(&mut self, value: String) -> AppRequest {
+        self.query = value;
+        AppRequest::RefreshView
+    }
+}
@@ -1,22 +1,29 @@
+use std::fmt::Display;
+
+use crossterm::event::{KeyEvent, KeyModifiers};
 use ratatui::crossterm::event::{Event, KeyCode, MouseButton, MouseEventKind};
 use ratatui::layout::{Position, Rect};
 use ratatui::widgets::TableState;
 use ratatui::Frame;
+use ratatui_helpers::keymap::{KeyMap, ShortCut};
 use ratatui_helpers::stateful_table::{IndexedRow, StatefulTable};
 use ratatui_helpers::view::View;
 use rrss_core::feed_manager::FeedManager;
-use rrss_core::filter::Filter;
+use rrss_core::filter::{Expr, Filter};
 use rrss_core::models::Tag;
 use rrss_core::sorter::Sorter;
 
 use super::centered_rect;
 use crate::app::{AppRequest, ViewKind};
+use crate::keymaps::{Bindings, ViewKeyMap};
 use crate::theme::StyledWidget;
 
 pub struct TagView<'row> {
     table: StatefulTable<'row, IndexedRow<Tag>>,
     filter: Filter,
+    base: Filter,
     sorter: Sorter<Tag>,
+    keymap: Bindings<TagsCommand>,
 }
 impl TagView<'_> {
     pub fn new(
@@ -31,8 +38,10 @@ impl TagView<'_> {
         let table = StyledWidget::indexed_table(fm.get_tags(&filter, &sorter), state, None);
         Self {
             table,
+            base: filter.clone(),
             filter,
             sorter,
+            keymap: Bindings::new::<TagsKeyMap>(),
         }
     }
 }
@@ -47,23 +56,38 @@ impl View for TagView<'_> {
         format!("rrss - tags")
     }
     fn refresh(&mut self, fm: &FeedManager) {
+        let base = self.base.clone();
         *self = Self::new(
             fm,
             self.filter.clone(),
             self.sorter.clone(),
             self.table.state().clone(),
         );
+        self.base = base;
     }
     fn update(&mut self, ev: &Event) -> AppRequest {
         self.table.update(ev);
         match ev {
-            Event::Key(ev) => match ev.code {
-                KeyCode::Char('r') => {
+            Event::Key(ev) => match self.keymap.get_command(ev) {
+                Some(TagsCommand::UpdateTag) => {
                     if let Some(id) = self.table.selected_value() {
                         return AppRequest::UpdateFeeds(Filter::new().tag_id(id.to_string()));
                     }
                 }
-                KeyCode::Enter => {
+                Some(TagsCommand::MarkTagAsRead) => {
+                    if let Some(id) = self.table.selected_value() {
+                        return AppRequest::MarkTagAsRead(id.clone());
+                    }
+                }
+                Some(TagsCommand::ViewTimeline) => {
+                    if let Some(id) = self.table.selected_value() {
+                        return AppRequest::CloseView
+                            + AppRequest::OpenTimelineView(
+                                Filter::new().unread_item().tag_id(id.clone()),
+                            );
+                    }
+                }
+                Some(TagsCommand::OpenTag) => {
                     if let Some(id) = self.table.selected_value() {
                         return AppRequest::CloseView
                             + AppRequest::OpenFeedView(
@@ -72,7 +96,7 @@ impl View for TagView<'_> {
                             );
                     }
                 }
-                _ => {}
+                None => {}
             },
             Event::Mouse(ev) => match ev.kind {
                 MouseEventKind::Up(MouseButton::Left) => {
@@ -116,7 +140,55 @@ impl View for TagView<'_> {
         AppRequest::None
     }
     fn on_prompt_change(&mut self, value: String) -> AppRequest {
-        self.filter.tag_contains = Some(value);
+        // an unfinished query keeps the last one that parsed
+        if value.trim().is_empty() {
+            self.filter = self.base.clone();
+        } else if let Ok(expr) = Expr::parse(&value) {
+            self.filter = self.base.clone().and_query(expr);
+        }
         AppRequest::RefreshView
     }
 }
+
+#[derive(Debug, Clone)]
+pub enum TagsCommand {
+    UpdateTag,
+    MarkTagAsRead,
+    ViewTimeline,
+    OpenTag,
+}
+impl Display for TagsCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+pub struct TagsKeyMap(pub Vec<ShortCut<TagsCommand>>);
+impl ViewKeyMap for TagsKeyMap {
+    const VIEW: &'static str = "tags";
+}
+impl KeyMap for TagsKeyMap {
+    type Command = TagsCommand;
+    fn get_shortcuts(&self) -> &[ShortCut<Self::Command>] {
+        &self.0
+    }
+    fn default() -> Self {
+        Self(Vec::from([
+            ShortCut(
+                TagsCommand::UpdateTag,
+                vec![KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                TagsCommand::MarkTagAsRead,
+                vec![KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE)],
+            ),
+            ShortCut(
+                TagsCommand::ViewTimeline,
+                vec![KeyEvent::new(KeyCode::Char('u'), KeyModifiers::SHIFT)],
+            ),
+            ShortCut(
+                TagsCommand::OpenTag,
+                vec![KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)],
+            ),
+        ]))
+    }
+}
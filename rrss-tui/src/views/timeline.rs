@@ -0,0 +1,152 @@
+use ratatui::crossterm::event::{Event, MouseButton, MouseEventKind};
+use ratatui::layout::{Position, Rect};
+use ratatui::widgets::TableState;
+use ratatui::Frame;
+use ratatui_helpers::stateful_table::{IndexedRow, StatefulTable};
+use ratatui_helpers::view::View;
+use rrss_core::feed_manager::FeedManager;
+use rrss_core::filter::{Expr, Filter};
+use rrss_core::models::{Item, TimelineItem};
+use rrss_core::sorter::Sorter;
+
+use super::items::{ItemsCommand, ItemsKeyMap};
+use crate::app::{AppRequest, ViewKind};
+use crate::keymaps::Bindings;
+use crate::theme::StyledWidget;
+
+pub struct TimelineView<'row> {
+    table: StatefulTable<'row, IndexedRow<TimelineItem>>,
+    base: Filter,
+    filter: Filter,
+    sorter: Sorter<Item>,
+    keymap: Bindings<ItemsCommand>,
+}
+impl TimelineView<'_> {
+    pub fn new(fm: &FeedManager, filter: Filter, mut state: TableState) -> Self {
+        if state.selected().is_none() {
+            state.select(Some(0));
+        }
+        let filter = filter.visible();
+        let sorter = Item::BY_POSTED_REV;
+        let items = fm.get_timeline(&filter, &sorter);
+        let kind = match filter.starred {
+            Some(true) => "starred",
+            _ => "unread",
+        };
+        let title = match &filter.tag_id {
+            Some(tag) => format!("{} {} items in: {}", items.len(), kind, tag),
+            None => format!("{} {} items", items.len(), kind),
+        };
+        Self {
+            table: StyledWidget::indexed_table(items, state, Some(title)),
+            base: filter.clone(),
+            filter,
+            sorter,
+            keymap: Bindings::new::<ItemsKeyMap>(),
+        }
+    }
+    fn view_selected(&self) -> AppRequest {
+        if let Some(idx) = self.table.selected_row() {
+            return AppRequest::OpenDetailedItemView(self.filter.clone(), self.sorter.clone(), idx);
+        }
+        AppRequest::None
+    }
+}
+impl View for TimelineView<'_> {
+    type Model = FeedManager;
+    type Signal = AppRequest;
+    type Kind = ViewKind;
+    fn kind(&self) -> Self::Kind {
+        ViewKind::Timeline
+    }
+    fn title(&self) -> String {
+        format!("rrss - timeline")
+    }
+    fn refresh(&mut self, fm: &FeedManager) {
+        let base = self.base.clone();
+        *self = Self::new(fm, self.filter.clone(), self.table.state().clone());
+        self.base = base;
+    }
+    fn update(&mut self, ev: &Event) -> AppRequest {
+        self.table.update(ev);
+        match ev {
+            Event::Key(ev) => {
+                if let Some(cmd) = self.keymap.get_command(ev) {
+                    match cmd {
+                        ItemsCommand::OpenItem => {
+                            if let Some(id) = self.table.selected_value() {
+                                return AppRequest::OpenItem(id.clone());
+                            }
+                        }
+                        ItemsCommand::ViewItem => return self.view_selected(),
+                        ItemsCommand::UpdateFeed => {
+                            let mut filter = Filter::new();
+                            filter.tag_id = self.base.tag_id.clone();
+                            return AppRequest::UpdateFeeds(filter);
+                        }
+                        ItemsCommand::ClearFilters => {
+                            self.filter = self.base.clone();
+                            return AppRequest::RefreshView;
+                        }
+                        ItemsCommand::MarkItemAsRead => {
+                            if let Some(id) = self.table.selected_value() {
+                                return AppRequest::MarkItemAsRead(id.clone());
+                            }
+                        }
+                        ItemsCommand::ToggleRead => {
+                            if let Some(id) = self.table.selected_value() {
+                                return AppRequest::ToggleItemRead(id.clone());
+                            }
+                        }
+                        ItemsCommand::ToggleStarred => {
+                            if let Some(id) = self.table.selected_value() {
+                                return AppRequest::ToggleItemStarred(id.clone());
+                            }
+                        }
+                        ItemsCommand::ViewItemInfo => {
+                            if let Some(id) = self.table.selected_value() {
+                                return AppRequest::OpenInfoItemView(id.clone());
+                            }
+                        }
+                        ItemsCommand::ViewItemLinks => {
+                            if let Some(id) = self.table.selected_value() {
+                                return AppRequest::OpenLinksView(
+                                    Filter::new().item_id(id.clone()),
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+            Event::Mouse(ev) => {
+                let pos = Position {
+                    x: ev.column,
+                    y: ev.row,
+                };
+                if let MouseEventKind::Up(MouseButton::Left) = ev.kind
+                    && let Some(row) = self.table.screen_coords_to_row_index(pos)
+                    && self.table.selected_row() == Some(row)
+                {
+                    return self.view_selected();
+                }
+            }
+            _ => {}
+        }
+        AppRequest::None
+    }
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) {
+        self.table.draw(f, area)
+    }
+    fn on_prompt_submit(&mut self, _value: String) -> AppRequest {
+        self.view_selected()
+    }
+    fn on_prompt_change(&mut self, value: String) -> AppRequest {
+        // an unfinished query keeps the last one that parsed
+        if value.trim().is_empty() {
+            self.filter = self.base.clone();
+        } else if let Ok(expr) = Expr::parse(&value) {
+            self.filter = self.base.clone().and_query(expr);
+        }
+        AppRequest::RefreshView
+    }
+}
@@ -8,11 +8,13 @@ use ratatui::widgets::{Paragraph, Wrap};
 use ratatui::Frame;
 use ratatui_helpers::keymap::{KeyMap, ShortCut};
 
+use crate::keymaps::{Bindings, ViewKeyMap};
+
 pub struct ScrollableParagraph<'a> {
     paragraph: Paragraph<'a>,
     scroll: u16,
     area: Rect,
-    keymap: ScrollableParagraphKeyMap,
+    keymap: Bindings<ScrollableParagraphCommand>,
 }
 impl<'a> ScrollableParagraph<'a> {
     pub fn new(content: impl Into<Text<'a>>) -> Self {
@@ -20,7 +22,7 @@ impl<'a> ScrollableParagraph<'a> {
             paragraph: Paragraph::new(content).wrap(Wrap::default()),
             scroll: 0,
             area: Rect::default(),
-            keymap: KeyMap::default(),
+            keymap: Bindings::new::<ScrollableParagraphKeyMap>(),
         }
     }
     pub fn draw(&mut self, f: &mut Frame, area: Rect) {
@@ -98,6 +100,9 @@ impl Display for ScrollableParagraphCommand {
     }
 }
 pub struct ScrollableParagraphKeyMap(pub Vec<ShortCut<ScrollableParagraphCommand>>);
+impl ViewKeyMap for ScrollableParagraphKeyMap {
+    const VIEW: &'static str = "scroll";
+}
 impl KeyMap for ScrollableParagraphKeyMap {
     type Command = ScrollableParagraphCommand;
     fn get_shortcuts(&self) -> &[ShortCut<Self::Command>] {